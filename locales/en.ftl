@@ -0,0 +1,80 @@
+### locales/en.ftl - English messages, the base every translation falls back to.
+### Translations live next to this file as <language>.ftl (e.g. de.ftl, pt-BR.ftl), are listed in
+### BUNDLED in src/i18n.rs and only need the messages they translate; rip also reads them from
+### ~/.local/share/rip/locales and /usr/share/rip/locales.
+
+## Trashing
+
+skipped = Skipped: { $path }
+holds-current-dir = Warning: { $path } holds the current directory, which will be left deleted under the shell
+trash-anyway = Trash it anyway? [y/N]{" "}
+deleted-permanently = Permanently deleted: { $path }
+deleted-volatile-trash = Permanently deleted: { $path } (the trash is { $reason } and would not survive)
+snapshot-saved = Snapshot of { $path } saved to the trash ({ $size }); rip --restore { $path } brings it back
+notify-trashed = Trashed { $count } items from { $from } (rip --undo puts them back)
+
+## Listing and tagging
+
+trash-empty = Trash is empty
+no-items = No items { $filter }
+list-total = { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    }, { $size }
+tagged = Tagged: { $path } [{ $tags }]
+
+## Permanent deletion
+
+nothing-deleted = Nothing deleted
+deleted-item = Permanently deleted: { $path } ({ $size })
+deleted-tagged = Permanently deleted { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    } tagged { $tags }
+confirm-purge = Permanently delete these { $count } items ({ $size })? [y/N]{" "}
+deleted-matching = Permanently deleted { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    } { $filter } ({ $size })
+confirm-delete-keep = Permanently delete { $count } items ({ $size }) and keep { $kept }? [y/N]{" "}
+deleted-kept = Permanently deleted { $count } items ({ $size }); { $kept } items remain in the trash
+freed-item = Deleted { $path } ({ $size })
+freed = Freed { $size } by permanently deleting { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    }
+kept-other-users = Kept { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    } trashed by other users
+strays-removed = Also removed { $count ->
+        [one] { $count } stray entry
+       *[other] { $count } stray entries
+    } in files/ that had no trash record
+strays-kept = Kept { $count ->
+        [one] { $count } stray entry
+       *[other] { $count } stray entries
+    } in files/ without a trash record; see rip --check
+trash-emptied = Trash emptied ({ $count } items, { $size })
+empty-partial = Deleted { $count } items ({ $size }); these could not be removed and are still in the trash:
+
+## Restoring
+
+restored = Restored: { $path }
+nothing-restored = Nothing restored
+nothing-trashed-window = Nothing was trashed { $what }
+confirm-restore = Restore these { $count } items trashed { $what }? [y/N]{" "}
+
+## History
+
+history-none = No trash operations recorded yet
+history-header = {" "}# Date & Time          Operation
+history-operation = trashed { $count ->
+        [one] { $count } item
+       *[other] { $count } items
+    } from { $from }
+history-failed = ({ $count } failed)
+history-none-left = [nothing left in the trash]
+history-some-left = [{ $count } still in the trash]
+undoing = Undoing: { $operation } ({ $date })
+undo-nothing = No recorded trash operation left to undo
@@ -0,0 +1,189 @@
+// src/archive.rs - Portable tarballs of trash items: expiry archives, export and import
+use crate::config;
+use crate::sudo;
+use crate::trash::{find_trash_dir, generate_unique_name, load_trash_items, TrashItem};
+use anyhow::{Context, Result};
+use chrono::Local;
+use nanoid::nanoid;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub fn archive_dir() -> Option<PathBuf> {
+    config::get().archive.as_deref().map(config::expand_path)
+}
+
+pub fn archive_items(items: &[&TrashItem]) -> Result<Option<PathBuf>> {
+    let Some(dir) = archive_dir() else {
+        return Ok(None);
+    };
+    if items.is_empty() {
+        return Ok(None);
+    }
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Cannot create archive directory {}", dir.display()))?;
+
+    let stamp = Local::now().format("%Y-%m-%d_%H%M%S");
+    let path = dir.join(format!("rip-expired-{stamp}.tar.zst"));
+    write_archive(&path, items)?;
+    Ok(Some(path))
+}
+
+pub fn write_archive(path: &Path, items: &[&TrashItem]) -> Result<()> {
+    let compressed = path.extension().is_some_and(|e| e == "zst");
+
+    let result = (|| -> Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let writer: Box<dyn Write> = if compressed {
+            Box::new(zstd::Encoder::new(file, 0)?.auto_finish())
+        } else {
+            Box::new(file)
+        };
+        let mut tar = tar::Builder::new(writer);
+        tar.follow_symlinks(false);
+        for item in items {
+            let trashed = item.trashed_path();
+            let name = PathBuf::from("files").join(&item.trashed_name);
+            if trashed.symlink_metadata()?.is_dir() {
+                tar.append_dir_all(&name, &trashed)?;
+            } else {
+                tar.append_path_with_name(&trashed, &name)?;
+            }
+            let info_name = PathBuf::from("info").join(format!("{}.trashinfo", item.trashed_name));
+            tar.append_path_with_name(&item.info_path, info_name)?;
+        }
+        tar.into_inner()?.flush()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(path);
+        return Err(e.context(format!("Cannot write archive {}", path.display())));
+    }
+    Ok(())
+}
+
+pub fn export_trash(path: &Path) -> Result<()> {
+    let items = load_trash_items()?;
+    let refs: Vec<&TrashItem> = items.iter().collect();
+    write_archive(path, &refs)?;
+    println!("Exported {} items to {}", items.len(), path.display());
+    Ok(())
+}
+
+pub fn import_trash(path: &Path) -> Result<()> {
+    let imported = import_archive(path)?;
+    println!("Imported {imported} items from {}", path.display());
+    Ok(())
+}
+
+pub fn import_archive(path: &Path) -> Result<usize> {
+    let trash = find_trash_dir()?;
+    let staging = trash.join(format!(".rip-import-{}", nanoid!(8)));
+    fs::create_dir_all(&staging)?;
+    let result = import_from_staging(path, &trash, &staging);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn import_from_staging(path: &Path, trash: &Path, staging: &Path) -> Result<usize> {
+    let file = BufReader::new(File::open(path).with_context(|| format!("Cannot open {}", path.display()))?);
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "zst") {
+        Box::new(zstd::Decoder::new(file)?)
+    } else {
+        Box::new(file)
+    };
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    archive.unpack(staging).with_context(|| format!("Cannot unpack {}", path.display()))?;
+
+    let mut imported = 0;
+    let Ok(entries) = fs::read_dir(staging.join("info")) else {
+        return Ok(0);
+    };
+    for entry in entries.flatten() {
+        let info = entry.path();
+        let Some(name) = info.file_stem().and_then(|s| s.to_str()).map(str::to_owned) else { continue };
+        let staged = staging.join("files").join(&name);
+        if staged.symlink_metadata().is_err() {
+            warn!("{name}: no matching file in archive, skipped");
+            continue;
+        }
+        let mut target_name = name.clone();
+        while trash.join("files").join(&target_name).symlink_metadata().is_ok()
+            || trash.join("info").join(format!("{target_name}.trashinfo")).exists()
+        {
+            target_name = generate_unique_name(Path::new(&name));
+        }
+        fs::rename(&staged, trash.join("files").join(&target_name))?;
+        let info_file = trash.join("info").join(format!("{target_name}.trashinfo"));
+        fs::write(&info_file, imported_info(&fs::read_to_string(&info)?))?;
+        sudo::hand_over(&info_file);
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+fn imported_info(info: &str) -> String {
+    let mut lines: String = info
+        .lines()
+        .filter(|line| !line.starts_with("X-Rip-TrashedBy=") && !line.starts_with("X-Rip-Inode="))
+        .map(|line| format!("{line}\n"))
+        .collect();
+    lines.push_str(&format!("X-Rip-TrashedBy={}\n", sudo::trash_uid()));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(tag: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rip-test-{}-{tag}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for sub in ["trash/files", "trash/info", "staging"] {
+                fs::create_dir_all(dir.join(sub)).unwrap();
+            }
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn append(builder: &mut tar::Builder<File>, path: &str, content: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content).unwrap();
+    }
+
+    #[test]
+    fn imports_items_trashed_by_another_uid_as_our_own() {
+        let tmp = TempDir::new("import-uid");
+        let archive = tmp.0.join("other-machine.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        let info = "[Trash Info]\nPath=/home/else/report.txt\nDeletionDate=2026-01-02T03:04:05\nX-Rip-Inode=2049:131075\nX-Rip-TrashedBy=4242\nX-Rip-Tags=work\n";
+        append(&mut builder, "info/report.txt.trashinfo", info.as_bytes());
+        append(&mut builder, "files/report.txt", b"quarterly");
+        builder.finish().unwrap();
+
+        let trash = tmp.0.join("trash");
+        assert_eq!(import_from_staging(&archive, &trash, &tmp.0.join("staging")).unwrap(), 1);
+
+        let imported = fs::read_to_string(trash.join("info/report.txt.trashinfo")).unwrap();
+        let trashed_by: Vec<_> = imported.lines().filter(|line| line.starts_with("X-Rip-TrashedBy=")).collect();
+        assert_eq!(trashed_by, [format!("X-Rip-TrashedBy={}", sudo::trash_uid())]);
+        assert!(!imported.contains("X-Rip-Inode="));
+        assert!(imported.contains("Path=/home/else/report.txt\n") && imported.contains("X-Rip-Tags=work\n"));
+        assert_eq!(fs::read_to_string(trash.join("files/report.txt")).unwrap(), "quarterly");
+    }
+}
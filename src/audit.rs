@@ -0,0 +1,73 @@
+// src/audit.rs - Audit trail of trash, restore, purge and empty operations (journald, syslog or a file)
+use crate::config;
+use crate::sudo;
+use chrono::{SecondsFormat, Utc};
+use std::ffi::CStr;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use tracing::warn;
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+fn user() -> String {
+    let uid = sudo::trash_uid();
+    let pw = unsafe { libc::getpwuid(uid) };
+    if pw.is_null() {
+        return uid.to_string();
+    }
+    unsafe { CStr::from_ptr((*pw).pw_name) }.to_string_lossy().into_owned()
+}
+
+fn journal_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+pub fn record(action: &str, path: &Path, size: Option<u64>) {
+    let Some(sink) = config::get().audit.as_deref() else {
+        return;
+    };
+    let user = user();
+    let path = path.to_string_lossy();
+    let size = size.map(|s| s.to_string()).unwrap_or_else(|| "-".to_owned());
+    let message = format!("user={user} action={action} path={path:?} size={size}");
+
+    let result = match sink {
+        "journald" => {
+            let mut buf = Vec::new();
+            journal_field(&mut buf, "MESSAGE", &format!("rip: {message}"));
+            journal_field(&mut buf, "SYSLOG_IDENTIFIER", "rip");
+            journal_field(&mut buf, "PRIORITY", "6");
+            journal_field(&mut buf, "RIP_ACTION", action);
+            journal_field(&mut buf, "RIP_USER", &user);
+            journal_field(&mut buf, "RIP_PATH", &path);
+            journal_field(&mut buf, "RIP_SIZE", &size);
+            UnixDatagram::unbound().and_then(|s| s.send_to(&buf, JOURNAL_SOCKET)).map(drop)
+        }
+        "syslog" => {
+            let line = format!("<86>rip[{}]: {message}", std::process::id());
+            UnixDatagram::unbound().and_then(|s| s.send_to(line.as_bytes(), SYSLOG_SOCKET)).map(drop)
+        }
+        file => {
+            let stamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(config::expand_path(file))
+                .and_then(|mut f| writeln!(f, "{stamp} {message}"))
+        }
+    };
+    if let Err(e) = result {
+        warn!("cannot write audit log to {sink}: {e}");
+    }
+}
@@ -0,0 +1,477 @@
+// src/check.rs - Consistency checks and repairs of the trash directories (--check, --adopt)
+use crate::fs_utils::{human_size, path_size};
+use crate::journal;
+use crate::sandbox;
+use crate::sudo::{self, trash_uid};
+use crate::trash::{
+    entry_names, find_trash_dir, forget_directory_size, format_deletion_date, parse_deletion_date, read_directory_sizes, record_directory_size,
+    set_info_field, trash_roots,
+};
+use crate::volume::trash_topdir;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration as StdDuration;
+use tracing::warn;
+use urlencoding::{decode_binary, encode_binary};
+
+const GC_GRACE: StdDuration = StdDuration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Issue {
+    Permissions,
+    OrphanFile,
+    DanglingInfo,
+    StrayInfo,
+    MalformedInfo,
+    BadPath,
+    BadDate,
+    SizeDrift,
+    MissingDirSize,
+    StaleDirSize,
+    Conflict,
+}
+
+impl Issue {
+    fn label(&self) -> &'static str {
+        match self {
+            Issue::Permissions => "permissions",
+            Issue::OrphanFile => "orphan file",
+            Issue::DanglingInfo => "dangling info",
+            Issue::StrayInfo => "stray file",
+            Issue::MalformedInfo => "malformed info",
+            Issue::BadPath => "bad path",
+            Issue::BadDate => "bad date",
+            Issue::SizeDrift => "size drift",
+            Issue::MissingDirSize => "missing size",
+            Issue::StaleDirSize => "stale size",
+            Issue::Conflict => "conflict",
+        }
+    }
+}
+
+pub struct Problem {
+    pub issue: Issue,
+    pub path: PathBuf,
+    pub detail: String,
+}
+
+fn problem(issue: Issue, path: &Path, detail: impl Into<String>) -> Problem {
+    Problem { issue, path: path.to_path_buf(), detail: detail.into() }
+}
+
+fn writable(dir: &Path) -> bool {
+    CString::new(dir.as_os_str().as_bytes())
+        .is_ok_and(|c| unsafe { libc::access(c.as_ptr(), libc::W_OK | libc::X_OK) } == 0)
+}
+
+fn valid_encoding(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if bytes.get(i + 1..i + 3).is_some_and(|h| h.iter().all(u8::is_ascii_hexdigit)) => i += 3,
+            b'%' => return false,
+            b if b.is_ascii_control() || b == b' ' => return false,
+            _ => i += 1,
+        }
+    }
+    true
+}
+
+fn check_permissions(trash: &Path, problems: &mut Vec<Problem>) -> bool {
+    for dir in [trash.to_path_buf(), trash.join("files"), trash.join("info")] {
+        match dir.symlink_metadata() {
+            Ok(meta) if !meta.is_dir() => problems.push(problem(Issue::Permissions, &dir, "not a directory")),
+            Ok(meta) if meta.uid() != trash_uid() => {
+                problems.push(problem(Issue::Permissions, &dir, format!("owned by uid {}", meta.uid())))
+            }
+            Ok(_) if !writable(&dir) => problems.push(problem(Issue::Permissions, &dir, "not writable")),
+            Ok(_) => {}
+            Err(e) => {
+                problems.push(problem(Issue::Permissions, &dir, e.to_string()));
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn check_info(info: &Path, content: &str, file: Option<&Path>, dir_size: Option<u64>, problems: &mut Vec<Problem>) {
+    let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next() != Some("[Trash Info]") {
+        problems.push(problem(Issue::MalformedInfo, info, "missing [Trash Info] header"));
+    }
+    let field = |key: &str| content.lines().find_map(|l| l.strip_prefix(key)).map(str::trim);
+    match field("Path=") {
+        None => problems.push(problem(Issue::MalformedInfo, info, "no Path= line")),
+        Some("") => problems.push(problem(Issue::BadPath, info, "empty Path=")),
+        Some(path) if !valid_encoding(path) => problems.push(problem(Issue::BadPath, info, format!("not URL-encoded: {path}"))),
+        Some(_) => {}
+    }
+    match field("DeletionDate=").map(|d| (d, parse_deletion_date(d))) {
+        None => problems.push(problem(Issue::MalformedInfo, info, "no DeletionDate= line")),
+        Some((date, None)) => problems.push(problem(Issue::BadDate, info, format!("unparseable DeletionDate={date}"))),
+        Some((date, Some(when))) if when > sandbox::now() + Duration::days(1) => {
+            problems.push(problem(Issue::BadDate, info, format!("DeletionDate={date} is in the future")))
+        }
+        Some(_) => {}
+    }
+    let Some((file, meta)) = file.and_then(|f| Some((f, f.symlink_metadata().ok()?))) else { return };
+    let transformed = field("X-Rip-Compressed=").is_some() || field("X-Rip-Encrypted=").is_some();
+    let recorded = field("X-Rip-Size=").and_then(|s| s.parse::<u64>().ok());
+    if meta.is_file() && !transformed {
+        if let Some(recorded) = recorded.filter(|&r| r != meta.len()) {
+            problems.push(problem(
+                Issue::SizeDrift,
+                info,
+                format!("X-Rip-Size={recorded} but the file holds {}", human_size(meta.len())),
+            ));
+        }
+    }
+    if meta.is_dir() && !transformed {
+        let actual = path_size(file).unwrap_or(0);
+        match dir_size {
+            None => problems.push(problem(Issue::MissingDirSize, info, "directory without a directorysizes entry")),
+            Some(cached) if cached != actual => problems.push(problem(
+                Issue::SizeDrift,
+                info,
+                format!("directorysizes says {} but the directory holds {}", human_size(cached), human_size(actual)),
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+pub fn check_trash(trash: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !check_permissions(trash, &mut problems) {
+        return problems;
+    }
+    let files_dir = trash.join("files");
+    let dir_sizes = read_directory_sizes(trash);
+    let mut described = HashSet::new();
+    let file_names = entry_names(&files_dir);
+    let info_names = entry_names(&trash.join("info"));
+
+    let mut infos: Vec<PathBuf> = fs::read_dir(trash.join("info"))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    infos.sort();
+    for info in infos {
+        if info.extension().is_none_or(|ext| ext != "trashinfo") {
+            problems.push(problem(Issue::StrayInfo, &info, "not a .trashinfo file"));
+            continue;
+        }
+        let name = info.file_stem().unwrap_or_default().to_os_string();
+        let file = files_dir.join(&name);
+        let exists = file.symlink_metadata().is_ok();
+        if !exists {
+            problems.push(problem(Issue::DanglingInfo, &info, format!("files/{} does not exist", name.to_string_lossy())));
+        }
+        if exists && !file_names.contains(&name) {
+            let actual = on_disk_name(&files_dir, &file).unwrap_or_default();
+            let detail = match info_names.contains(&record_name(&actual)) {
+                true => format!("duplicate of the record for files/{}", actual.to_string_lossy()),
+                false => format!("resolves to files/{} under a different name", actual.to_string_lossy()),
+            };
+            problems.push(problem(Issue::Conflict, &info, detail));
+            described.insert(actual);
+        }
+        described.insert(name.clone());
+        match fs::read_to_string(&info) {
+            Ok(content) => check_info(&info, &content, exists.then_some(file.as_path()), dir_sizes.get(&*name.to_string_lossy()).copied(), &mut problems),
+            Err(e) => problems.push(problem(Issue::MalformedInfo, &info, e.to_string())),
+        }
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(&files_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.sort();
+    for file in files {
+        let name = file.file_name().unwrap_or_default();
+        if name.as_bytes().starts_with(b".rip-") || described.contains(name) {
+            continue;
+        }
+        problems.push(problem(Issue::OrphanFile, &file, "no matching .trashinfo record"));
+    }
+
+    let mut stale: Vec<&String> = dir_sizes.keys().filter(|name| !files_dir.join(name).exists()).collect();
+    stale.sort();
+    for name in stale {
+        problems.push(problem(Issue::StaleDirSize, &trash.join("directorysizes"), format!("entry for missing {name}")));
+    }
+    problems
+}
+
+fn record_name(name: &OsStr) -> OsString {
+    let mut record = name.to_os_string();
+    record.push(".trashinfo");
+    record
+}
+
+fn on_disk_name(files_dir: &Path, file: &Path) -> Option<OsString> {
+    let meta = file.symlink_metadata().ok()?;
+    fs::read_dir(files_dir).ok()?.filter_map(|e| e.ok()).find_map(|e| {
+        let other = e.metadata().ok()?;
+        (other.dev() == meta.dev() && other.ino() == meta.ino()).then(|| e.file_name())
+    })
+}
+
+fn original_name(trashed: &str) -> String {
+    let (stem, ext) = match trashed.rsplit_once('.') {
+        Some((stem, ext)) if stem.len() > 11 => (stem, format!(".{ext}")),
+        _ => (trashed, String::new()),
+    };
+    match stem.len().checked_sub(11).filter(|&cut| stem.is_char_boundary(cut) && stem.as_bytes()[cut] == b'_') {
+        Some(cut) => format!("{}{ext}", &stem[..cut]),
+        None => trashed.to_owned(),
+    }
+}
+
+fn modified(path: &Path) -> String {
+    let mtime = path.symlink_metadata().and_then(|m| m.modified()).map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+    format_deletion_date(mtime)
+}
+
+pub fn adopt_file(trash: &Path, file: &Path, original: Option<&Path>) -> Result<PathBuf> {
+    let name = file.file_name().context("no file name")?.to_string_lossy().into_owned();
+    let home_trash = find_trash_dir()?;
+    let original = match original {
+        Some(path) => path.to_path_buf(),
+        None if trash == home_trash => dirs_next::home_dir().unwrap_or_default().join(original_name(&name)),
+        None => PathBuf::from(original_name(&name)),
+    };
+    let recorded = match trash_topdir(trash) {
+        Some(topdir) if trash != home_trash => original.strip_prefix(topdir).unwrap_or(&original).to_path_buf(),
+        _ => original.clone(),
+    };
+    let meta = file.symlink_metadata()?;
+    let mut info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\nX-Rip-Adopted=true\n",
+        encode_binary(recorded.as_os_str().as_bytes()),
+        modified(file)
+    );
+    if !meta.is_dir() {
+        info.push_str(&format!("X-Rip-Size={}\n", meta.len()));
+    }
+    let info_path = trash.join("info").join(format!("{name}.trashinfo"));
+    if info_path.exists() {
+        return Err(anyhow!("{} already exists", info_path.display()));
+    }
+    fs::write(&info_path, info)?;
+    sudo::hand_over(&info_path);
+    if let (true, Ok(size)) = (meta.is_dir(), path_size(file)) {
+        record_directory_size(trash, &name, size, &info_path)?;
+    }
+    Ok(original)
+}
+
+fn repair(trash: &Path, p: &Problem) -> Result<Option<String>> {
+    let stem = p.path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let file = trash.join("files").join(&stem);
+    if !matches!(p.issue, Issue::OrphanFile) && p.path.symlink_metadata().is_err() {
+        return Ok(Some("record already removed".to_owned()));
+    }
+    Ok(match p.issue {
+        Issue::Permissions | Issue::StrayInfo => None,
+        Issue::OrphanFile => {
+            if in_flight(trash, &p.path) {
+                return Err(anyhow!("written in the last hour or still journaled, possibly still in progress; left alone"));
+            }
+            let original = adopt_file(trash, &p.path, None)?;
+            Some(format!("recorded with original path {}", original.display()))
+        }
+        Issue::DanglingInfo => {
+            fs::remove_file(&p.path)?;
+            Some("removed".to_owned())
+        }
+        Issue::MalformedInfo => {
+            let content = fs::read_to_string(&p.path).unwrap_or_default();
+            let has = |key: &str| content.lines().any(|l| l.starts_with(key));
+            if !has("Path=") || !file.exists() {
+                fs::remove_file(&p.path)?;
+                if file.symlink_metadata().is_ok() {
+                    adopt_file(trash, &file, None)?;
+                    Some("replaced with a regenerated record".to_owned())
+                } else {
+                    Some("removed".to_owned())
+                }
+            } else {
+                let body: Vec<&str> = content.lines().filter(|l| l.trim() != "[Trash Info]").collect();
+                let mut fixed = format!("[Trash Info]\n{}\n", body.join("\n"));
+                if !has("DeletionDate=") {
+                    fixed.push_str(&format!("DeletionDate={}\n", modified(&file)));
+                }
+                fs::write(&p.path, fixed)?;
+                Some("rewrote the record".to_owned())
+            }
+        }
+        Issue::BadPath => {
+            let content = fs::read_to_string(&p.path)?;
+            let raw = content.lines().find_map(|l| l.strip_prefix("Path=")).unwrap_or("").trim();
+            if raw.is_empty() {
+                return Ok(None);
+            }
+            let encoded = encode_binary(&decode_binary(raw.as_bytes())).into_owned();
+            set_info_field(&p.path, "Path", &encoded)?;
+            Some(format!("re-encoded as {encoded}"))
+        }
+        Issue::BadDate => {
+            let date = modified(&file);
+            set_info_field(&p.path, "DeletionDate", &date)?;
+            Some(format!("DeletionDate set to {date} (modification time)"))
+        }
+        Issue::SizeDrift | Issue::MissingDirSize => {
+            let meta = file.symlink_metadata()?;
+            if meta.is_dir() {
+                let size = path_size(&file).unwrap_or(0);
+                record_directory_size(trash, &stem, size, &p.path)?;
+                Some(format!("directorysizes updated to {}", human_size(size)))
+            } else {
+                set_info_field(&p.path, "X-Rip-Size", &meta.len().to_string())?;
+                Some(format!("X-Rip-Size updated to {}", meta.len()))
+            }
+        }
+        Issue::Conflict => {
+            let actual = on_disk_name(&trash.join("files"), &file).context("the trashed file is gone")?;
+            let record = trash.join("info").join(record_name(&actual));
+            if entry_names(&trash.join("info")).contains(&record_name(&actual)) {
+                fs::remove_file(&p.path)?;
+                Some(format!("removed; {} is kept", record.display()))
+            } else {
+                fs::rename(&p.path, &record)?;
+                Some(format!("renamed to {}", record.display()))
+            }
+        }
+        Issue::StaleDirSize => {
+            let name = p.detail.strip_prefix("entry for missing ").unwrap_or_default();
+            forget_directory_size(trash, name)?;
+            Some("entry removed".to_owned())
+        }
+    })
+}
+
+fn settled(path: &Path) -> bool {
+    let age = path.symlink_metadata().and_then(|m| m.modified()).ok().and_then(|t| t.elapsed().ok());
+    age.is_some_and(|age| age >= GC_GRACE)
+}
+
+fn in_flight(trash: &Path, file: &Path) -> bool {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    !settled(file) || journal::in_progress(trash, &name)
+}
+
+fn leftovers(trash: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for dir in [trash.to_path_buf(), trash.join("info")] {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(".directorysizes.") || name.starts_with(".rip-probe-") || name.ends_with(".trashinfo.tmp") {
+                found.push(entry.path());
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+pub fn run_gc() -> Result<()> {
+    let mut cleaned = 0;
+    for trash in trash_roots()? {
+        for p in check_trash(&trash) {
+            if !matches!(p.issue, Issue::DanglingInfo | Issue::StaleDirSize) {
+                continue;
+            }
+            if p.issue == Issue::DanglingInfo && !settled(&p.path) {
+                println!("Skipped {}: written in the last hour, possibly still in progress", p.path.display());
+                continue;
+            }
+            match repair(&trash, &p) {
+                Ok(Some(done)) => {
+                    println!("{}: {} ({}): {done}", p.issue.label(), p.path.display(), p.detail);
+                    cleaned += 1;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("{}: {e}", p.path.display()),
+            }
+        }
+        for leftover in leftovers(&trash).into_iter().filter(|path| settled(path)) {
+            match fs::remove_file(&leftover) {
+                Ok(()) => {
+                    println!("temporary file: {}: removed", leftover.display());
+                    cleaned += 1;
+                }
+                Err(e) => warn!("{}: {e}", leftover.display()),
+            }
+        }
+    }
+    match cleaned {
+        0 => println!("Nothing to clean up"),
+        n => println!("{n} entries cleaned up"),
+    }
+    Ok(())
+}
+
+pub fn run_adopt(dir: Option<&Path>) -> Result<()> {
+    let dir = dir.map(std::path::absolute).transpose()?;
+    let mut adopted = 0;
+    for trash in trash_roots()? {
+        for p in check_trash(&trash).into_iter().filter(|p| matches!(p.issue, Issue::OrphanFile)) {
+            if in_flight(&trash, &p.path) {
+                println!("Skipped {}: written in the last hour or still journaled, possibly still in progress", p.path.display());
+                continue;
+            }
+            let name = p.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let original = dir.as_ref().map(|d| d.join(original_name(&name)));
+            match adopt_file(&trash, &p.path, original.as_deref()) {
+                Ok(original) => {
+                    println!("Adopted {} (restores to {})", p.path.display(), original.display());
+                    adopted += 1;
+                }
+                Err(e) => warn!("{}: {e}", p.path.display()),
+            }
+        }
+    }
+    match adopted {
+        0 => println!("No stray files found"),
+        n => println!("{n} stray files adopted"),
+    }
+    Ok(())
+}
+
+pub fn run_check(repair_problems: bool) -> Result<bool> {
+    let mut total = 0;
+    let mut repaired = 0;
+    for trash in trash_roots()? {
+        let problems = check_trash(&trash);
+        println!("{}: {}", trash.display(), if problems.is_empty() { "ok" } else { "inconsistent" });
+        for p in &problems {
+            println!("  {:<15} {}: {}", p.issue.label(), p.path.display(), p.detail);
+            if !repair_problems {
+                continue;
+            }
+            match repair(&trash, p) {
+                Ok(Some(done)) => {
+                    println!("  {:<15} {done}", "  repaired");
+                    repaired += 1;
+                }
+                Ok(None) => println!("  {:<15} needs manual attention", "  not repaired"),
+                Err(e) => println!("  {:<15} {e}", "  repair failed"),
+            }
+        }
+        total += problems.len();
+    }
+    match (total, repair_problems) {
+        (0, _) => println!("No problems found"),
+        (n, false) => println!("{n} problems found (run with --repair to fix them)"),
+        (n, true) => println!("{n} problems found, {repaired} repaired"),
+    }
+    Ok(total == repaired)
+}
@@ -0,0 +1,116 @@
+// src/compress.rs - Transparent zstd compression of aged trash items
+use crate::config;
+use crate::duration::Span;
+use crate::sandbox;
+use crate::trash::{set_info_field, TrashItem};
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use tracing::warn;
+
+pub const COMPRESSED_MARKER: &str = "tar+zstd";
+const LEVEL: i32 = 9;
+
+pub fn compress_after() -> Option<Span> {
+    config::get().compress_after.as_deref().and_then(|s| Span::parse(s).ok())
+}
+
+pub fn compression_candidates(items: &[TrashItem]) -> Vec<&TrashItem> {
+    let Some(span) = compress_after() else {
+        return Vec::new();
+    };
+    let cutoff = span.before(sandbox::now());
+    items
+        .iter()
+        .filter(|i| i.compressed.is_none() && i.encrypted.is_none() && i.deletion_date < cutoff)
+        .filter(|i| {
+            i.trashed_path()
+                .symlink_metadata()
+                .is_ok_and(|m| !m.file_type().is_symlink())
+        })
+        .collect()
+}
+
+pub fn compress_aged(items: &[TrashItem]) -> usize {
+    let mut compressed = 0;
+    for item in compression_candidates(items) {
+        match compress_item(item) {
+            Ok(()) => compressed += 1,
+            Err(e) => warn!("cannot compress {}: {e}", item.original_path.display()),
+        }
+    }
+    if compressed > 0 {
+        println!("Compressed {compressed} aged items");
+    }
+    compressed
+}
+
+pub fn compress_item(item: &TrashItem) -> Result<()> {
+    let trash = item.trash_dir();
+    let trashed = item.trashed_path();
+    let tmp = trash.join(format!(".{}.rip-compress", item.trashed_name));
+    let old = trash.join(format!(".{}.rip-old", item.trashed_name));
+
+    let metadata = trashed.symlink_metadata()?;
+    let result = (|| -> Result<()> {
+        let encoder = zstd::Encoder::new(BufWriter::new(File::create(&tmp)?), LEVEL)?;
+        let encoder = pack_archive(&trashed, encoder)?;
+        encoder.finish()?.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    fs::rename(&trashed, &old)?;
+    fs::rename(&tmp, &trashed)?;
+    set_info_field(&item.info_path, "X-Rip-Compressed", COMPRESSED_MARKER)?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(&old)?;
+    } else {
+        fs::remove_file(&old)?;
+    }
+    Ok(())
+}
+
+pub fn pack_archive<W: Write>(src: &Path, writer: W) -> Result<W> {
+    let metadata = src.symlink_metadata()?;
+    let mut archive = tar::Builder::new(writer);
+    archive.follow_symlinks(false);
+    if metadata.is_dir() {
+        archive.append_dir_all(".", src)?;
+    } else {
+        let name = src.file_name().context("Path has no file name")?;
+        archive.append_path_with_name(src, name)?;
+    }
+    Ok(archive.into_inner()?)
+}
+
+pub fn decompress_to(src: &Path, marker: &str, target: &Path) -> Result<()> {
+    if marker != COMPRESSED_MARKER {
+        return Err(anyhow!("Unsupported compression format: {marker}"));
+    }
+    let decoder = zstd::Decoder::new(BufReader::new(File::open(src)?))?;
+    unpack_archive(decoder, target)
+}
+
+pub fn unpack_archive<R: Read>(reader: R, target: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+
+    let mut entries = archive.entries()?;
+    let mut first = entries.next().context("Empty compressed item")??;
+    if first.header().entry_type().is_dir() {
+        fs::create_dir_all(target)?;
+        first.unpack_in(target)?;
+        for entry in entries {
+            entry?.unpack_in(target)?;
+        }
+    } else {
+        first.unpack(target)?;
+    }
+    Ok(())
+}
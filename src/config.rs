@@ -0,0 +1,374 @@
+// src/config.rs - Persistent settings loaded from ~/.config/rip/config.toml
+use crate::schedule::Schedule;
+use crate::duration::Span;
+use crate::policy::parse_keep_policy;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use toml_edit::{DocumentMut, Item, TableLike};
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub trash_dir: Option<String>,
+    pub fallback_trash_dirs: Vec<String>,
+    pub keep: Option<String>,
+    pub schedule: Option<String>,
+    pub notifications: Option<bool>,
+    pub compress_after: Option<String>,
+    pub encrypt: Option<bool>,
+    pub dedup: Option<bool>,
+    pub archive: Option<String>,
+    pub sync: Option<String>,
+    pub metrics: Option<String>,
+    pub audit: Option<String>,
+    pub policy_script: Option<String>,
+    pub network_fs: Option<String>,
+    pub ephemeral: Option<String>,
+    pub s3: Option<S3Config>,
+    pub no_confirm: Option<bool>,
+    pub project_trash: Option<bool>,
+    pub normalize_unicode: Option<bool>,
+    pub never_expire: Vec<String>,
+    pub watch: Vec<String>,
+    pub trashes: BTreeMap<String, NamedTrash>,
+    pub policy: BTreeMap<String, DirectoryPolicy>,
+    pub hooks: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NamedTrash {
+    pub path: String,
+    pub keep: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "s3"), allow(dead_code))]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: Option<String>,
+    pub prefix: Option<String>,
+    pub access_key: Option<String>,
+    pub secret_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DirectoryPolicy {
+    pub keep: Option<String>,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+pub fn config_path() -> Result<PathBuf> {
+    Ok(dirs_next::config_dir()
+        .context("Cannot determine config directory")?
+        .join("rip/config.toml"))
+}
+
+pub fn expand_path(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs_next::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => dirs_next::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+pub fn normalized(path: &Path) -> Cow<'_, Path> {
+    match path.to_str() {
+        Some(text) if get().normalize_unicode == Some(true) => Cow::Owned(PathBuf::from(text.nfc().collect::<String>())),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+pub fn active_profile() -> Option<&'static str> {
+    OVERRIDES.get().and_then(|o| o.profile.as_deref())
+}
+
+pub fn active_trash() -> Option<&'static str> {
+    OVERRIDES.get().and_then(|o| o.trash.as_deref())
+}
+
+pub fn root_trash() -> bool {
+    OVERRIDES.get().is_some_and(|o| o.root_trash)
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    fs::read_to_string(path)
+        .with_context(|| format!("Cannot read {}", path.display()))?
+        .parse()
+        .with_context(|| format!("Invalid config {}", path.display()))
+}
+
+fn apply_profile(table: &mut toml::Table, name: &str, path: &Path) -> Result<()> {
+    let mut profiles = match table.remove("profiles") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        _ => toml::Table::new(),
+    };
+    let Some(toml::Value::Table(profile)) = profiles.remove(name) else {
+        let known: Vec<&String> = profiles.keys().collect();
+        return Err(anyhow!("No profile named {name:?} in {} (available: {known:?})", path.display()));
+    };
+    for (key, value) in profile {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => base.extend(overlay),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+const ENV_STRINGS: &[(&str, &str)] = &[
+    ("RIP_TRASH_DIR", "trash_dir"),
+    ("RIP_KEEP", "keep"),
+    ("RIP_SCHEDULE", "schedule"),
+    ("RIP_COMPRESS_AFTER", "compress_after"),
+    ("RIP_ARCHIVE", "archive"),
+    ("RIP_SYNC", "sync"),
+];
+
+const ENV_FLAGS: &[(&str, &str)] = &[
+    ("RIP_NO_CONFIRM", "no_confirm"),
+    ("RIP_NOTIFICATIONS", "notifications"),
+    ("RIP_ENCRYPT", "encrypt"),
+    ("RIP_DEDUP", "dedup"),
+    ("RIP_PROJECT_TRASH", "project_trash"),
+    ("RIP_NORMALIZE_UNICODE", "normalize_unicode"),
+];
+
+fn apply_env(table: &mut toml::Table) -> Result<()> {
+    for (var, key) in ENV_STRINGS {
+        if let Some(value) = env::var(var).ok().filter(|v| !v.is_empty()) {
+            let valid = match *key {
+                "keep" => parse_keep_policy(&value).map(drop),
+                "schedule" => Schedule::parse(&value).map(drop),
+                "compress_after" => Span::parse(&value).map(drop),
+                _ => Ok(()),
+            };
+            valid.with_context(|| format!("Invalid {var}"))?;
+            table.insert((*key).to_owned(), toml::Value::String(value));
+        }
+    }
+    for (var, key) in ENV_FLAGS {
+        if let Some(value) = env::var(var).ok().filter(|v| !v.is_empty()) {
+            let flag = match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => return Err(anyhow!("Invalid {var}: {value} (expected 1 or 0)")),
+            };
+            table.insert((*key).to_owned(), toml::Value::Boolean(flag));
+        }
+    }
+    Ok(())
+}
+
+fn apply_named_trash(table: &mut toml::Table, name: &str, path: &Path) -> Result<()> {
+    let trash = table
+        .get("trashes")
+        .and_then(|t| t.get(name))
+        .and_then(toml::Value::as_table)
+        .ok_or_else(|| anyhow!("No trash named {name:?} in {}", path.display()))?
+        .clone();
+    let location = trash
+        .get("path")
+        .and_then(toml::Value::as_str)
+        .ok_or_else(|| anyhow!("Trash {name:?} has no path in {}", path.display()))?;
+    table.insert("trash_dir".to_owned(), toml::Value::String(location.to_owned()));
+    if let Some(keep) = trash.get("keep") {
+        table.insert("keep".to_owned(), keep.clone());
+    }
+    Ok(())
+}
+
+fn project_trash_dir() -> Option<PathBuf> {
+    env::current_dir()
+        .ok()?
+        .ancestors()
+        .map(|dir| dir.join(".riptrash"))
+        .find(|trash| trash.is_dir())
+}
+
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub profile: Option<String>,
+    pub trash: Option<String>,
+    pub trash_dir: Option<PathBuf>,
+    pub root_trash: bool,
+}
+
+impl Overrides {
+    fn profile(&self) -> Option<String> {
+        self.profile.clone().or_else(|| env::var("RIP_PROFILE").ok().filter(|p| !p.is_empty()))
+    }
+}
+
+pub fn load(overrides: &Overrides) -> Result<Config> {
+    let path = config_path().unwrap_or_default();
+    let mut table = read_table(&path)?;
+    match overrides.profile() {
+        Some(name) => apply_profile(&mut table, &name, &path)?,
+        None => {
+            table.remove("profiles");
+        }
+    }
+    apply_env(&mut table)?;
+    if table.get("project_trash").and_then(toml::Value::as_bool) == Some(true) && env::var_os("RIP_TRASH_DIR").is_none() {
+        if let Some(dir) = project_trash_dir() {
+            table.insert("trash_dir".to_owned(), toml::Value::String(dir.to_string_lossy().into_owned()));
+        }
+    }
+    if let Some(name) = &overrides.trash {
+        apply_named_trash(&mut table, name, &path)?;
+    }
+    if let Some(dir) = &overrides.trash_dir {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.clone());
+        table.insert("trash_dir".to_owned(), toml::Value::String(dir.to_string_lossy().into_owned()));
+    }
+    let config: Config = table
+        .try_into()
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+    if let Some(keep) = &config.keep {
+        parse_keep_policy(keep)
+            .with_context(|| format!("Invalid keep policy in {}", path.display()))?;
+    }
+    for (dir, rule) in &config.policy {
+        if let Some(keep) = &rule.keep {
+            parse_keep_policy(keep)
+                .with_context(|| format!("Invalid keep policy for {dir} in {}", path.display()))?;
+        }
+    }
+    for (name, trash) in &config.trashes {
+        if let Some(keep) = &trash.keep {
+            parse_keep_policy(keep)
+                .with_context(|| format!("Invalid keep policy for trash {name} in {}", path.display()))?;
+        }
+    }
+    for pattern in &config.never_expire {
+        glob::Pattern::new(pattern)
+            .with_context(|| format!("Invalid never_expire pattern {pattern:?} in {}", path.display()))?;
+    }
+    for rule in &config.watch {
+        crate::watch::parse_watch_rule(rule)
+            .with_context(|| format!("Invalid watch rule {rule:?} in {}", path.display()))?;
+    }
+    if let Some(hook) = config.hooks.keys().find(|hook| !crate::hooks::HOOKS.contains(&hook.as_str())) {
+        return Err(anyhow!("Unknown hook {hook:?} in {} (expected one of {})", path.display(), crate::hooks::HOOKS.join(", ")));
+    }
+    if let Some(network_fs) = &config.network_fs {
+        crate::volume::parse_network_fs(network_fs)
+            .with_context(|| format!("Invalid network_fs in {}", path.display()))?;
+    }
+    if let Some(ephemeral) = &config.ephemeral {
+        crate::ephemeral::parse_ephemeral(ephemeral)
+            .with_context(|| format!("Invalid ephemeral in {}", path.display()))?;
+    }
+    if let Some(span) = &config.compress_after {
+        Span::parse(span)
+            .with_context(|| format!("Invalid compress_after in {}", path.display()))?;
+    }
+    if let Some(schedule) = &config.schedule {
+        Schedule::parse(schedule)
+            .with_context(|| format!("Invalid schedule in {}", path.display()))?;
+    }
+    Ok(config)
+}
+
+pub fn init(mut overrides: Overrides) -> Result<()> {
+    let config = load(&overrides)?;
+    overrides.profile = overrides.profile();
+    let _ = OVERRIDES.set(overrides);
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| load(&Overrides::default()).unwrap_or_default())
+}
+
+pub fn set_value(key: &str, value: &str) -> Result<()> {
+    set_entry(&[key], value.into())
+}
+
+fn edit_entry(text: &str, sections: &[&str], key: &str, value: toml_edit::Value) -> Result<String> {
+    let mut document: DocumentMut = text.parse()?;
+    let mut target = document.as_table_mut() as &mut dyn TableLike;
+    for section in sections {
+        let mut table = toml_edit::Table::new();
+        table.set_implicit(true);
+        target = target
+            .entry(section)
+            .or_insert(Item::Table(table))
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("{section:?} is not a section"))?;
+    }
+    match target.get_mut(key) {
+        Some(Item::Value(existing)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        _ => {
+            target.insert(key, Item::Value(value));
+        }
+    }
+    Ok(document.to_string())
+}
+
+pub fn set_entry(keys: &[&str], value: toml_edit::Value) -> Result<()> {
+    let path = config_path()?;
+    let text = match path.exists() {
+        true => fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))?,
+        false => String::new(),
+    };
+    let (key, sections) = keys.split_last().context("empty config key")?;
+    let profile = active_profile().map(|name| ["profiles", name]);
+    let sections: Vec<&str> = profile.iter().flatten().chain(sections).copied().collect();
+    let edited = edit_entry(&text, &sections, key, value).with_context(|| format!("Invalid config {}", path.display()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, edited).with_context(|| format!("Cannot write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# how long to keep trashed files\nkeep = \"30d\"   # a month\n\n[policy.\"~/Downloads\"]\n# downloads go sooner\nkeep = \"7d\"\n";
+
+    #[test]
+    fn setting_a_value_keeps_comments_and_order() {
+        let edited = edit_entry(CONFIG, &[], "keep", "90d".into()).unwrap();
+        assert_eq!(edited, CONFIG.replace("keep = \"30d\"", "keep = \"90d\""));
+    }
+
+    #[test]
+    fn setting_a_nested_value_adds_only_what_is_missing() {
+        let edited = edit_entry(CONFIG, &["profiles", "work", "policy", "~/Downloads"], "keep", "1d".into()).unwrap();
+        assert!(edited.starts_with(CONFIG), "{edited}");
+        assert!(!edited.contains("[profiles]\n") && !edited.contains("[profiles.work]\n"), "{edited}");
+        let table: toml::Table = edited.parse().unwrap();
+        assert_eq!(table["profiles"]["work"]["policy"]["~/Downloads"]["keep"].as_str(), Some("1d"));
+        assert_eq!(table["policy"]["~/Downloads"]["keep"].as_str(), Some("7d"));
+    }
+
+    #[test]
+    fn refuses_to_nest_under_a_plain_value() {
+        assert!(edit_entry(CONFIG, &["keep"], "x", "1d".into()).is_err());
+    }
+}
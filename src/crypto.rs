@@ -0,0 +1,248 @@
+// src/crypto.rs - Optional encryption of trashed contents at rest (ChaCha20-Poly1305 STREAM)
+use crate::compress::{pack_archive, unpack_archive};
+use crate::config;
+use crate::trash::iter_trash_items;
+use anyhow::{anyhow, Context, Result};
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::{aead::rand_core::RngCore, ChaCha20Poly1305, Key};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use tracing::warn;
+
+pub const ENCRYPTED_MARKER: &str = "chacha20poly1305";
+const MAGIC: &[u8; 8] = b"RIPENC1\0";
+const NONCE_PREFIX: usize = 7;
+const CHUNK: usize = 64 * 1024;
+const TAG: usize = 16;
+
+pub fn encryption_enabled() -> bool {
+    config::get().encrypt == Some(true)
+}
+
+fn key_file() -> Result<PathBuf> {
+    Ok(dirs_next::config_dir()
+        .context("Cannot determine config directory")?
+        .join("rip/trash.key"))
+}
+
+enum Keyring {
+    Key(String),
+    Empty,
+    Unavailable,
+}
+
+fn keyring_lookup() -> Keyring {
+    let output = match Command::new("secret-tool").args(["lookup", "application", "rip", "type", "trash-key"]).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Keyring::Empty,
+        Err(_) => return Keyring::Unavailable,
+    };
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    match (output.status.success() && !text.is_empty(), output.stderr.is_empty()) {
+        (true, _) => Keyring::Key(text),
+        (false, true) => Keyring::Empty,
+        (false, false) => Keyring::Unavailable,
+    }
+}
+
+fn keyring_store(encoded: &str) -> bool {
+    let child = Command::new("secret-tool")
+        .args(["store", "--label=rip trash encryption key", "application", "rip", "type", "trash-key"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else { return false };
+    let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(encoded.as_bytes()).is_ok());
+    written && child.wait().is_ok_and(|s| s.success())
+}
+
+fn load_or_create_key() -> Result<Key> {
+    let path = key_file()?;
+    if let Ok(text) = fs::read_to_string(&path) {
+        let bytes = hex::decode(text.trim()).ok().filter(|b| b.len() == 32)
+            .ok_or_else(|| anyhow!("Corrupt key file {}", path.display()))?;
+        return Ok(*Key::from_slice(&bytes));
+    }
+    let keyring = keyring_lookup();
+    if let Keyring::Key(text) = &keyring {
+        let bytes = hex::decode(text).ok().filter(|b| b.len() == 32)
+            .ok_or_else(|| anyhow!("Corrupt trash key in the keyring (secret-tool lookup application rip type trash-key)"))?;
+        return Ok(*Key::from_slice(&bytes));
+    }
+    if iter_trash_items()?.any(|item| item.encrypted.is_some()) {
+        return Err(match keyring {
+            Keyring::Unavailable => anyhow!("The keyring holding the trash key is not available in this session (no secret service); cannot read or add encrypted trash items"),
+            _ => anyhow!("The trash holds encrypted items, but there is no trash key in the keyring or {}", path.display()),
+        });
+    }
+
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    let encoded = hex::encode(key);
+    if matches!(keyring, Keyring::Unavailable) || !keyring_store(&encoded) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .with_context(|| format!("Cannot create key file {}", path.display()))?;
+        file.write_all(encoded.as_bytes())?;
+        warn!("no keyring available, stored trash key in {}", path.display());
+    }
+    Ok(key)
+}
+
+fn key() -> Result<&'static Key> {
+    static KEY: OnceLock<Key> = OnceLock::new();
+    if let Some(key) = KEY.get() {
+        return Ok(key);
+    }
+    let key = load_or_create_key()?;
+    Ok(KEY.get_or_init(|| key))
+}
+
+fn crypto_error(_: chacha20poly1305::aead::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "encrypted trash data is corrupt or the key is wrong")
+}
+
+pub struct EncryptWriter<W: Write> {
+    inner: W,
+    encryptor: Option<EncryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub fn new(mut inner: W) -> Result<Self> {
+        let mut nonce = [0u8; NONCE_PREFIX];
+        OsRng.fill_bytes(&mut nonce);
+        inner.write_all(MAGIC)?;
+        inner.write_all(&nonce)?;
+        let cipher = ChaCha20Poly1305::new(key()?);
+        Ok(EncryptWriter {
+            inner,
+            encryptor: Some(EncryptorBE32::from_aead(cipher, nonce.as_ref().into())),
+            buffer: Vec::with_capacity(CHUNK),
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let encryptor = self.encryptor.take().expect("finish called once");
+        let sealed = encryptor.encrypt_last(self.buffer.as_slice()).map_err(crypto_error)?;
+        self.inner.write_all(&sealed)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() > CHUNK {
+            let encryptor = self.encryptor.as_mut().expect("writer not finished");
+            let sealed = encryptor.encrypt_next(&self.buffer[..CHUNK]).map_err(crypto_error)?;
+            self.inner.write_all(&sealed)?;
+            self.buffer.drain(..CHUNK);
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    decryptor: Option<DecryptorBE32<ChaCha20Poly1305>>,
+    pending: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; MAGIC.len() + NONCE_PREFIX];
+        inner.read_exact(&mut header).context("Truncated encrypted item")?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(anyhow!("Not an encrypted rip item"));
+        }
+        let cipher = ChaCha20Poly1305::new(key()?);
+        let nonce = &header[MAGIC.len()..];
+        Ok(DecryptReader {
+            inner,
+            decryptor: Some(DecryptorBE32::from_aead(cipher, nonce.into())),
+            pending: Vec::new(),
+            plain: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let want = CHUNK + TAG + 1;
+        let mut buf = [0u8; 8192];
+        while self.pending.len() < want {
+            let limit = (want - self.pending.len()).min(buf.len());
+            let n = self.inner.read(&mut buf[..limit])?;
+            if n == 0 {
+                break;
+            }
+            self.pending.extend_from_slice(&buf[..n]);
+        }
+        self.pos = 0;
+        if self.pending.len() > CHUNK + TAG {
+            let decryptor = self.decryptor.as_mut().expect("stream not finished");
+            self.plain = decryptor.decrypt_next(&self.pending[..CHUNK + TAG]).map_err(crypto_error)?;
+            self.pending.drain(..CHUNK + TAG);
+        } else {
+            let decryptor = self.decryptor.take().expect("stream not finished");
+            self.plain = decryptor.decrypt_last(self.pending.as_slice()).map_err(crypto_error)?;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = out.len().min(self.plain.len() - self.pos);
+        out[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+pub fn encrypt_into(src: &Path, dest: &Path) -> Result<()> {
+    key()?;
+    let result = (|| -> Result<()> {
+        let writer = EncryptWriter::new(BufWriter::new(File::create(dest)?))?;
+        let writer = pack_archive(src, writer)?;
+        writer.finish()?.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result.with_context(|| format!("Cannot encrypt {}", src.display()))
+}
+
+pub fn decrypt_to(src: &Path, marker: &str, target: &Path) -> Result<()> {
+    if marker != ENCRYPTED_MARKER {
+        return Err(anyhow!("Unsupported encryption format: {marker}"));
+    }
+    let reader = DecryptReader::new(BufReader::new(File::open(src)?))?;
+    unpack_archive(reader, target)
+}
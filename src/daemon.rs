@@ -0,0 +1,161 @@
+// src/daemon.rs - Background mode that applies the auto-clean policy on a schedule and runs the watch rules
+use crate::dbus;
+use crate::metrics;
+use crate::schedule;
+use crate::policy::apply_keep_policy;
+use crate::trash::{iter_trash_items, state_dir, trash_roots, TrashItem};
+use crate::watch::Watcher;
+use anyhow::{anyhow, Context, Result};
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::process;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::error;
+
+const TICK: Duration = Duration::from_secs(60);
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn pid_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("daemon.pid"))
+}
+
+// The daemon holds an exclusive flock on its pid file for as long as it runs, so a
+// stale file left by a crash (or a recycled pid) never counts as a running daemon.
+fn locked(file: &File) -> bool {
+    let held = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } != 0;
+    held && io::Error::last_os_error().raw_os_error() == Some(libc::EWOULDBLOCK)
+}
+
+fn running_pid() -> Option<u32> {
+    let mut file = File::open(pid_file().ok()?).ok()?;
+    let mut pid = String::new();
+    file.read_to_string(&mut pid).ok()?;
+    let pid: u32 = pid.trim().parse().ok()?;
+    (pid != process::id() && locked(&file)).then_some(pid)
+}
+
+pub fn daemon_running() -> bool {
+    running_pid().is_some()
+}
+
+struct PidFile {
+    path: PathBuf,
+    _file: File,
+}
+
+static PID_PATH: OnceLock<CString> = OnceLock::new();
+
+extern "C" fn stop(_: libc::c_int) {
+    if let Some(path) = PID_PATH.get() {
+        unsafe { libc::unlink(path.as_ptr()) };
+    }
+    unsafe { libc::_exit(0) };
+}
+
+impl PidFile {
+    fn acquire() -> Result<Self> {
+        let path = pid_file()?;
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let pid = fs::read_to_string(&path).unwrap_or_default();
+            return Err(anyhow!("rip daemon is already running (pid {})", pid.trim()));
+        }
+        file.set_len(0)?;
+        write!(file, "{}", process::id()).with_context(|| format!("Cannot write {}", path.display()))?;
+        let _ = PID_PATH.set(CString::new(path.as_os_str().as_bytes())?);
+        for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+            unsafe { libc::signal(signal, stop as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+        }
+        Ok(PidFile { path, _file: file })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct WarmIndex {
+    items: Vec<TrashItem>,
+    info_mtimes: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl WarmIndex {
+    fn refresh(&mut self) -> Result<bool> {
+        let mtimes: Vec<_> = trash_roots()?
+            .into_iter()
+            .map(|trash| {
+                let mtime = fs::metadata(trash.join("info")).and_then(|m| m.modified()).ok();
+                (trash, mtime)
+            })
+            .collect();
+        if mtimes != self.info_mtimes {
+            let started = Instant::now();
+            self.items = iter_trash_items()?.collect();
+            self.info_mtimes = mtimes;
+            let bytes = self.items.iter().filter_map(|i| i.size).sum();
+            metrics::record_index(self.items.len(), bytes, started.elapsed());
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+pub fn run_daemon() -> Result<()> {
+    let _pid_file = PidFile::acquire()?;
+    println!("rip daemon started (pid {})", process::id());
+
+    let bus = dbus::serve();
+    metrics::serve()?;
+    let mut index = WarmIndex { items: Vec::new(), info_mtimes: Vec::new() };
+    let mut watcher = Watcher::start();
+    let mut last_cleanup: Option<Instant> = None;
+    let mut first = true;
+    loop {
+        match index.refresh() {
+            Ok(true) if !first => {
+                if let Some(bus) = &bus {
+                    bus.changed();
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("cannot refresh the trash index: {e}"),
+        }
+        first = false;
+        let due = if schedule::has_schedule() {
+            schedule::cleanup_due()
+        } else {
+            last_cleanup.is_none_or(|t| t.elapsed() >= CLEANUP_INTERVAL)
+        };
+        if due {
+            let started = Instant::now();
+            match apply_keep_policy(&index.items, false) {
+                Ok((deleted, bytes)) => {
+                    metrics::record_cleanup(deleted, bytes, started.elapsed());
+                    if deleted > 0 {
+                        index.info_mtimes.clear();
+                    }
+                }
+                Err(e) => error!("auto-clean failed: {e}"),
+            }
+            if let Err(e) = schedule::record_cleanup() {
+                error!("cannot record the auto-clean run: {e}");
+            }
+            last_cleanup = Some(Instant::now());
+        }
+        match &mut watcher {
+            Some(watcher) => {
+                watcher.sweep();
+                watcher.wait(TICK);
+            }
+            None => thread::sleep(TICK),
+        }
+    }
+}
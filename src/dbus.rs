@@ -0,0 +1,124 @@
+// src/dbus.rs - D-Bus interface served by the daemon (org.rip.Trash1 on the session bus)
+
+#[cfg(feature = "dbus")]
+mod service {
+    use crate::trash::{iter_trash_items, move_to_trash, purge_item, restore_item, trash_order, InfoBatch, TrashItem};
+    use zbus::blocking::Connection;
+    use zbus::fdo;
+    use zbus::object_server::SignalEmitter;
+
+    pub const BUS_NAME: &str = "org.rip.Trash";
+    pub const OBJECT_PATH: &str = "/org/rip/Trash";
+    pub const INTERFACE: &str = "org.rip.Trash1";
+
+    fn failed(e: anyhow::Error) -> fdo::Error {
+        fdo::Error::Failed(format!("{e:#}"))
+    }
+
+    fn find_item(name: &str) -> fdo::Result<TrashItem> {
+        iter_trash_items()
+            .map_err(failed)?
+            .find(|item| item.trashed_name == name)
+            .ok_or_else(|| fdo::Error::InvalidArgs(format!("No such item: {name}")))
+    }
+
+    pub struct TrashService;
+
+    #[zbus::interface(name = "org.rip.Trash1")]
+    impl TrashService {
+        fn list(&self) -> fdo::Result<Vec<(String, String, i64, u64)>> {
+            let mut items: Vec<_> = iter_trash_items().map_err(failed)?.collect();
+            items.sort_by(|a, b| trash_order(b, a));
+            Ok(items
+                .into_iter()
+                .map(|item| {
+                    (
+                        item.trashed_name,
+                        item.original_path.to_string_lossy().into_owned(),
+                        item.deletion_date.timestamp(),
+                        item.size.unwrap_or(0),
+                    )
+                })
+                .collect())
+        }
+
+        fn stats(&self) -> fdo::Result<(u32, u64)> {
+            let (count, bytes) = iter_trash_items()
+                .map_err(failed)?
+                .fold((0u32, 0u64), |(n, b), item| (n + 1, b + item.size.unwrap_or(0)));
+            Ok((count, bytes))
+        }
+
+        async fn trash(&self, paths: Vec<String>, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<u32> {
+            let mut batch = InfoBatch::new();
+            let mut trashed = 0;
+            let mut errors = Vec::new();
+            for path in &paths {
+                match move_to_trash(path, &mut batch) {
+                    Ok(()) => trashed += 1,
+                    Err(e) => errors.push(format!("{path}: {e}")),
+                }
+            }
+            batch.flush().map_err(failed)?;
+            Self::changed(&emitter).await?;
+            if !errors.is_empty() {
+                return Err(fdo::Error::Failed(errors.join("; ")));
+            }
+            Ok(trashed)
+        }
+
+        async fn restore(&self, name: &str, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<String> {
+            let item = find_item(name)?;
+            let target = restore_item(&item).map_err(failed)?;
+            Self::changed(&emitter).await?;
+            Ok(target.to_string_lossy().into_owned())
+        }
+
+        async fn purge(&self, name: &str, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+            let item = find_item(name)?;
+            purge_item(&item).map_err(failed)?;
+            Self::changed(&emitter).await?;
+            Ok(())
+        }
+
+        #[zbus(signal)]
+        async fn changed(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+    }
+
+    pub struct Bus(Connection);
+
+    impl Bus {
+        pub fn changed(&self) {
+            let _ = self.0.emit_signal(None::<()>, OBJECT_PATH, INTERFACE, "Changed", &());
+        }
+    }
+
+    pub fn serve() -> Option<Bus> {
+        let connection = zbus::blocking::connection::Builder::session()
+            .and_then(|b| b.name(BUS_NAME))
+            .and_then(|b| b.serve_at(OBJECT_PATH, TrashService))
+            .and_then(|b| b.build());
+        match connection {
+            Ok(connection) => Some(Bus(connection)),
+            Err(e) => {
+                tracing::warn!("D-Bus service unavailable: {e}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "dbus"))]
+mod service {
+    pub struct Bus;
+
+    impl Bus {
+        pub fn changed(&self) {}
+    }
+
+    pub fn serve() -> Option<Bus> {
+        None
+    }
+}
+
+pub use service::serve;
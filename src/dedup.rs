@@ -0,0 +1,60 @@
+// src/dedup.rs - Hardlinks identical file contents inside the trash to save space
+use crate::config;
+use crate::trash::{iter_trash_items, TrashItem};
+use anyhow::Result;
+use nanoid::nanoid;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+pub fn dedup_enabled() -> bool {
+    config::get().dedup == Some(true)
+}
+
+pub fn file_digest(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub fn deduplicated(item: &TrashItem) -> bool {
+    let (Some(digest), Ok(meta)) = (&item.sha256, item.trashed_path().symlink_metadata()) else {
+        return false;
+    };
+    if !meta.is_file() || meta.nlink() == 1 {
+        return false;
+    }
+    let same_inode = |other: &TrashItem| other.trashed_path().symlink_metadata().is_ok_and(|m| m.dev() == meta.dev() && m.ino() == meta.ino());
+    iter_trash_items().is_ok_and(|mut items| {
+        items.any(|other| other.sha256.as_ref() == Some(digest) && other.trashed_path() != item.trashed_path() && same_inode(&other))
+    })
+}
+
+pub fn link_duplicate(dest: &Path, digest: &str) -> Result<bool> {
+    let (Some(files_dir), Ok(meta)) = (dest.parent(), dest.symlink_metadata()) else {
+        return Ok(false);
+    };
+    for item in iter_trash_items()? {
+        if item.sha256.as_deref() != Some(digest) || item.compressed.is_some() || item.encrypted.is_some() {
+            continue;
+        }
+        let existing = item.trashed_path();
+        if existing.parent() != Some(files_dir) {
+            continue;
+        }
+        let Ok(existing_meta) = existing.symlink_metadata() else { continue };
+        if !existing_meta.is_file() || existing_meta.len() != meta.len() || existing_meta.ino() == meta.ino() {
+            continue;
+        }
+        let temp = files_dir.join(format!(".rip-link-{}", nanoid!(8)));
+        fs::hard_link(&existing, &temp)?;
+        if let Err(e) = fs::rename(&temp, dest) {
+            let _ = fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
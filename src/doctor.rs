@@ -0,0 +1,184 @@
+// src/doctor.rs - Environment checks (--doctor) with suggested fixes
+use crate::config;
+use crate::daemon::daemon_running;
+use crate::journal;
+use crate::sudo::trash_uid;
+use crate::trash::find_trash_dir;
+use crate::volume::{check_shared_root, filesystem_type, mount_points, trash_candidates, SharedRoot};
+use std::env;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+const REMOVABLE_ROOTS: &[&str] = &["/media", "/run/media", "/mnt"];
+
+const TRASH_TOOLS: &[(&str, &str)] = &[
+    ("trash-empty", "trash-cli empties the same trash but ignores never_expire and [policy] rules"),
+    ("gio", "`gio trash --empty` empties the same trash but ignores never_expire and [policy] rules"),
+    ("gtrash", "gtrash shares the trash; its prune ignores never_expire and [policy] rules"),
+    ("dolphin", "Dolphin empties the same trash but ignores never_expire and [policy] rules"),
+];
+
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn ok(&self, message: &str) {
+        println!("ok    {message}");
+    }
+
+    fn warn(&mut self, message: &str, fix: &str) {
+        println!("warn  {message}");
+        println!("      fix: {fix}");
+        self.warnings += 1;
+    }
+
+    fn fail(&mut self, message: &str, fix: &str) {
+        println!("FAIL  {message}");
+        println!("      fix: {fix}");
+        self.failures += 1;
+    }
+}
+
+fn writable(path: &Path) -> bool {
+    CString::new(path.as_os_str().as_bytes()).is_ok_and(|c| unsafe { libc::access(c.as_ptr(), libc::W_OK) } == 0)
+}
+
+fn read_only(path: &Path) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else { return false };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    let status = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    status == 0 && stat.f_flag & libc::ST_RDONLY != 0
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+fn check_trash(report: &mut Report, trash: &Path, label: &str) {
+    for sub in ["files", "info"] {
+        let dir = trash.join(sub);
+        match dir.symlink_metadata() {
+            Ok(meta) if !meta.is_dir() => {
+                report.fail(&format!("{} is not a directory", dir.display()), "move it away, then run rip --check --repair");
+                return;
+            }
+            Ok(meta) if meta.uid() != trash_uid() => {
+                report.fail(&format!("{} belongs to uid {}", dir.display(), meta.uid()), &format!("sudo chown -R {} {}", trash_uid(), trash.display()));
+                return;
+            }
+            Ok(_) if !writable(&dir) => {
+                report.fail(&format!("{} is not writable", dir.display()), &format!("chmod u+rwx {}", dir.display()));
+                return;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                report.warn(&format!("{} is missing", dir.display()), "it is created on the next trash operation");
+                return;
+            }
+        }
+    }
+    let mode = trash.metadata().map(|m| m.mode() & 0o777).unwrap_or(0);
+    if mode & 0o077 != 0 {
+        report.warn(
+            &format!("{} is accessible by other users (mode {mode:o})", trash.display()),
+            &format!("chmod 700 {}", trash.display()),
+        );
+    } else {
+        report.ok(&format!("{label} {} is writable and private", trash.display()));
+    }
+    let pending = journal::pending(trash);
+    if pending > 0 {
+        report.warn(
+            &format!("{pending} interrupted trash operations are pending in {}", trash.display()),
+            "run any rip command to finish or roll them back",
+        );
+    }
+}
+
+fn check_volumes(report: &mut Report, home_trash: &Path) {
+    let mut seen: Vec<u64> = home_trash.metadata().map(|m| m.dev()).into_iter().collect();
+    for topdir in mount_points() {
+        let Ok(dev) = topdir.metadata().map(|m| m.dev()) else { continue };
+        if seen.contains(&dev) || read_only(&topdir) {
+            continue;
+        }
+        seen.push(dev);
+        let shared_root = topdir.join(".Trash");
+        let [shared, private] = trash_candidates(&topdir);
+        if let SharedRoot::Rejected(reason) = check_shared_root(&shared_root) {
+            report.warn(
+                &format!("{} is {reason}, so it is ignored", shared_root.display()),
+                &format!("sudo chown root {0} && sudo chmod 1777 {0} (or remove it if nothing uses it)", shared_root.display()),
+            );
+        }
+        let existing: Vec<_> = [shared, private].into_iter().filter(|trash| trash.symlink_metadata().is_ok()).collect();
+        for trash in &existing {
+            check_trash(report, trash, "Volume trash");
+        }
+        let removable = REMOVABLE_ROOTS.iter().any(|root| topdir.starts_with(root));
+        if existing.is_empty() && removable && !writable(&topdir) {
+            let fstype = filesystem_type(&topdir.join(".")).unwrap_or_default();
+            report.warn(
+                &format!("{} ({fstype}) has no usable trash; files there are copied to the home trash", topdir.display()),
+                &format!("sudo mkdir -m 1777 {}", shared_root.display()),
+            );
+        }
+    }
+}
+
+fn check_locale(report: &mut Report) {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_else(|| "C".to_owned());
+    let lower = locale.to_lowercase();
+    if lower.contains("utf-8") || lower.contains("utf8") {
+        report.ok(&format!("Locale {locale} uses UTF-8"));
+    } else {
+        report.warn(
+            &format!("Locale {locale} is not UTF-8; non-ASCII names may show garbled in listings and other tools"),
+            "export LANG=C.UTF-8 (or your language's .UTF-8 locale)",
+        );
+    }
+}
+
+pub fn run_doctor(config_error: Option<anyhow::Error>) -> bool {
+    let mut report = Report::default();
+    let config_file = config::config_path().ok();
+    match (config_error, &config_file) {
+        (Some(e), _) => report.fail(&format!("{e:#}"), "correct the setting named above, or remove it to use the default"),
+        (None, Some(path)) if path.exists() => report.ok(&format!("Config {} is valid", path.display())),
+        (None, _) => report.ok("No config file; using the defaults"),
+    }
+
+    match find_trash_dir() {
+        Ok(trash) => {
+            check_trash(&mut report, &trash, "Trash");
+            check_volumes(&mut report, &trash);
+        }
+        Err(e) => report.fail(&format!("{e:#}"), "set trash_dir or fallback_trash_dirs in the config"),
+    }
+
+    if !config::get().watch.is_empty() && !daemon_running() {
+        report.warn("watch rules are configured but rip --daemon is not running", "start rip --daemon (e.g. from your session autostart)");
+    }
+
+    for (tool, caveat) in TRASH_TOOLS {
+        if on_path(tool) {
+            println!("note  {caveat}");
+        }
+    }
+    check_locale(&mut report);
+
+    println!();
+    match (report.failures, report.warnings) {
+        (0, 0) => println!("No problems found"),
+        (failures, warnings) => println!("{failures} problems, {warnings} warnings"),
+    }
+    report.failures == 0
+}
@@ -0,0 +1,88 @@
+// src/duration.rs - Human-friendly time spans (12h, 2w, 6m, "2 weeks") with calendar-aware months
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Months, Utc};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Span {
+    Minutes(i64),
+    Hours(i64),
+    Days(i64),
+    Weeks(i64),
+    Months(u32),
+    Years(u32),
+}
+
+impl Span {
+    pub fn parse(text: &str) -> Result<Self> {
+        let s = text.trim().to_lowercase();
+        let s = s.strip_suffix(" ago").unwrap_or(&s).trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let n: i64 = number
+            .parse()
+            .map_err(|_| anyhow!("Invalid duration: {text} (try 12h, 30d, 2w, 6m, 1y)"))?;
+        if n < 0 {
+            return Err(anyhow!("Invalid duration: {text}"));
+        }
+        let months = |n: i64| u32::try_from(n).map_err(|_| anyhow!("Duration too large: {text}"));
+        Ok(match unit.trim() {
+            "min" | "mins" | "minute" | "minutes" => Span::Minutes(n),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Span::Hours(n),
+            "d" | "day" | "days" => Span::Days(n),
+            "w" | "week" | "weeks" => Span::Weeks(n),
+            "m" | "mo" | "month" | "months" => Span::Months(months(n)?),
+            "y" | "year" | "years" => Span::Years(months(n)?),
+            _ => return Err(anyhow!("Unknown duration unit in '{text}' (use h, d, w, m or y)")),
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            Span::Minutes(n) | Span::Hours(n) | Span::Days(n) | Span::Weeks(n) => n == 0,
+            Span::Months(n) | Span::Years(n) => n == 0,
+        }
+    }
+
+    pub fn before(&self, t: DateTime<Utc>) -> DateTime<Utc> {
+        let calendar = |months: u32| t.checked_sub_months(Months::new(months));
+        let elapsed = |span: Option<Duration>| span.and_then(|span| t.checked_sub_signed(span));
+        match *self {
+            Span::Minutes(n) => elapsed(Duration::try_minutes(n)),
+            Span::Hours(n) => elapsed(Duration::try_hours(n)),
+            Span::Days(n) => elapsed(Duration::try_days(n)),
+            Span::Weeks(n) => elapsed(Duration::try_weeks(n)),
+            Span::Months(n) => calendar(n),
+            Span::Years(n) => calendar(n.saturating_mul(12)),
+        }
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (n, unit) = match *self {
+            Span::Minutes(n) => (n, "minute"),
+            Span::Hours(n) => (n, "hour"),
+            Span::Days(n) => (n, "day"),
+            Span::Weeks(n) => (n, "week"),
+            Span::Months(n) => (n as i64, "month"),
+            Span::Years(n) => (n as i64, "year"),
+        };
+        write!(f, "{n} {unit}{}", if n == 1 { "" } else { "s" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_spans_reach_back_to_the_earliest_date_instead_of_panicking() {
+        let now = Utc::now();
+        for text in ["99999999999999d", "9999999999999h", "9223372036854775807min", "999999999w", "4294967295y"] {
+            assert_eq!(Span::parse(text).unwrap().before(now), DateTime::<Utc>::MIN_UTC, "{text}");
+        }
+        assert_eq!(Span::parse("2d").unwrap().before(now), now - Duration::days(2));
+    }
+}
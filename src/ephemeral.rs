@@ -0,0 +1,57 @@
+// src/ephemeral.rs - Containers and RAM-backed trashes whose contents vanish with the instance
+use crate::config;
+use crate::volume::{filesystem_type, is_mount_point};
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MOUNTED_TRASH: &str = "/trash";
+const VOLATILE_FILESYSTEMS: &[&str] = &["tmpfs", "ramfs", "overlay", "aufs"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ephemeral {
+    Trash,
+    Delete,
+}
+
+pub fn parse_ephemeral(text: &str) -> Result<Ephemeral> {
+    match text.trim().to_lowercase().as_str() {
+        "trash" => Ok(Ephemeral::Trash),
+        "delete" => Ok(Ephemeral::Delete),
+        other => Err(anyhow!("Invalid ephemeral {other:?} (expected trash or delete)")),
+    }
+}
+
+pub fn ephemeral_policy() -> Ephemeral {
+    config::get()
+        .ephemeral
+        .as_deref()
+        .and_then(|p| parse_ephemeral(p).ok())
+        .unwrap_or(Ephemeral::Trash)
+}
+
+pub fn in_container() -> bool {
+    Path::new("/.dockerenv").exists()
+        || Path::new("/run/.containerenv").exists()
+        || env::var_os("container").is_some_and(|c| !c.is_empty())
+        || fs::read_to_string("/proc/1/cgroup")
+            .is_ok_and(|c| ["docker", "kubepods", "containerd", "libpod", "lxc"].iter().any(|k| c.contains(k)))
+}
+
+pub fn mounted_trash() -> Option<PathBuf> {
+    let dir = Path::new(MOUNTED_TRASH);
+    (in_container() && is_mount_point(dir)).then(|| dir.to_path_buf())
+}
+
+pub fn volatile_reason(trash: &Path) -> Option<String> {
+    if mounted_trash().is_some_and(|mounted| trash.starts_with(mounted)) {
+        return None;
+    }
+    if in_container() {
+        return Some("in a container".to_owned());
+    }
+    filesystem_type(&trash.join("info"))
+        .filter(|fstype| VOLATILE_FILESYSTEMS.contains(&fstype.as_str()))
+        .map(|fstype| format!("on {fstype}"))
+}
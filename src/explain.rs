@@ -0,0 +1,202 @@
+// src/explain.rs - Pre-flight descriptions (--explain) of what an invocation would do, without doing it
+use crate::config;
+use crate::crypto::encryption_enabled;
+use crate::ephemeral::{ephemeral_policy, volatile_reason, Ephemeral};
+use crate::fs_utils::{disk_usage, human_size, path_size};
+use crate::filter::ItemFilter;
+use crate::git;
+use crate::policy::{keep_policy, KeepPolicy};
+use crate::schedule;
+use crate::trash::{contains_current_dir, find_trash_dir, has_tags, iter_trash_items, nth_item, refuse_trash_itself, trash_roots};
+use crate::volume::{network_fs_policy, planned_volume_trash, remote_filesystem, NetworkFs};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+fn describe_kind(path: &Path, meta: &std::fs::Metadata) -> String {
+    if meta.file_type().is_symlink() {
+        let target = std::fs::read_link(path).map(|t| t.display().to_string()).unwrap_or_default();
+        return format!("symlink to {target} (the link is trashed, not its target)");
+    }
+    if !meta.is_dir() {
+        return format!("file, {}", human_size(meta.len()));
+    }
+    let (mut files, mut dirs) = (0, 0);
+    for entry in WalkDir::new(path).min_depth(1).into_iter().flatten() {
+        match entry.file_type().is_dir() {
+            true => dirs += 1,
+            false => files += 1,
+        }
+    }
+    format!("directory with {files} files and {dirs} subdirectories, {}", human_size(path_size(path).unwrap_or(0)))
+}
+
+fn same_filesystem(meta: &std::fs::Metadata, trash: &Path) -> bool {
+    trash.ancestors().find_map(|dir| dir.metadata().ok()).is_some_and(|m| m.dev() == meta.dev())
+}
+
+fn hook_line(hook: &str) {
+    if let Some(command) = config::get().hooks.get(hook) {
+        println!("  {hook} hook runs: {command}");
+    }
+}
+
+pub fn explain_trash(files: &[String], git_check: bool, cwd_check: bool) -> Result<()> {
+    let home_trash = find_trash_dir()?;
+    let auto_confirm = config::get().no_confirm == Some(true);
+    let mut copies: BTreeMap<PathBuf, u64> = BTreeMap::new();
+    let mut prompts = 0;
+    println!("rip would:");
+    if !crate::daemon::daemon_running() && schedule::cleanup_due() && !matches!(keep_policy(), KeepPolicy::Never) {
+        println!("  first apply the auto-clean policy ({}), which is due", keep_policy());
+    }
+    hook_line("pre_trash");
+    for path in files {
+        let target = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+        let Ok(meta) = target.symlink_metadata() else {
+            println!("  {path}: does not exist; rip reports an error and continues with the rest");
+            if path.contains(['*', '?', '[']) {
+                let matches: Vec<_> = glob::glob(path).into_iter().flatten().flatten().collect();
+                println!("    rip does not expand wildcards; an unquoted pattern would let the shell pass {} paths", matches.len());
+                for matched in matches.iter().take(5) {
+                    println!("      {}", matched.display());
+                }
+            }
+            continue;
+        };
+        println!("  {path}: {}", describe_kind(&target, &meta));
+        let volume_trash = match config::get().trash_dir {
+            None => planned_volume_trash(&target, &home_trash),
+            Some(_) => None,
+        };
+        let trash = volume_trash.clone().unwrap_or_else(|| home_trash.clone());
+        if let Err(e) = refuse_trash_itself(&target, &[&trash, &home_trash]) {
+            println!("    refused: {e}");
+            continue;
+        }
+        if git_check {
+            for (repo, problems) in git::unsaved_repositories(&target) {
+                println!("    prompt: {} is a git repository with {} (--no-git-check skips this)", repo.display(), problems.join(" and "));
+                prompts += 1;
+            }
+        }
+        if cwd_check && contains_current_dir(&target) {
+            println!("    prompt: it holds the current directory, which would be left deleted under the shell (-f skips this)");
+            prompts += 1;
+        }
+        let size = path_size(&target).unwrap_or(0);
+        if let (None, None, Some(fstype)) = (&volume_trash, &config::get().trash_dir, remote_filesystem(&target, &home_trash)) {
+            match network_fs_policy() {
+                NetworkFs::Volume => {
+                    println!("    refused: it is on a {fstype} network filesystem without a usable trash (network_fs = \"volume\")");
+                    continue;
+                }
+                NetworkFs::Delete => {
+                    println!("    prompt: it is on a {fstype} network filesystem; confirming deletes it permanently, declining leaves it");
+                    prompts += 1;
+                    continue;
+                }
+                NetworkFs::Copy => {}
+            }
+        }
+        if ephemeral_policy() == Ephemeral::Delete {
+            if let Some(reason) = volatile_reason(&trash) {
+                println!("    deleted permanently: the trash is {reason} (ephemeral = \"delete\")");
+                continue;
+            }
+        }
+        let label = match &volume_trash {
+            Some(_) => "volume trash",
+            None => "trash",
+        };
+        let created = if trash.join("files").is_dir() { "" } else { ", created on first use" };
+        println!("    {label}: {}{created}", trash.display());
+        if encryption_enabled() && !meta.file_type().is_symlink() {
+            println!("    method: encrypted copy into the trash ({}), then the original is removed", human_size(size));
+            *copies.entry(trash).or_default() += size;
+        } else if same_filesystem(&meta, &trash) {
+            println!("    method: rename on the same filesystem; instant and needs no extra space");
+        } else {
+            println!("    method: copy across filesystems ({}), verified, then the original is removed", human_size(size));
+            *copies.entry(trash).or_default() += size;
+        }
+        println!("    restore with: rip --restore {}", target.display());
+    }
+    hook_line("post_trash");
+    if prompts > 0 && auto_confirm {
+        println!("  the {prompts} prompts above are answered yes automatically (no_confirm is set)");
+    }
+    if copies.is_empty() {
+        println!("Projected space: no extra space, every target is renamed in place");
+    }
+    for (trash, bytes) in &copies {
+        match disk_usage(trash.ancestors().find(|dir| dir.exists()).unwrap_or(trash)) {
+            Ok(usage) if usage.available < *bytes => {
+                println!("Projected space: {} into {}, but only {} is free; the copy would fail", human_size(*bytes), trash.display(), human_size(usage.available));
+            }
+            Ok(usage) => {
+                println!("Projected space: {} into {} ({} free)", human_size(*bytes), trash.display(), human_size(usage.available));
+            }
+            Err(_) => println!("Projected space: {} into {}", human_size(*bytes), trash.display()),
+        }
+    }
+    println!("Nothing was changed.");
+    Ok(())
+}
+
+pub fn explain_empty(tags: &[String], shred: bool) -> Result<()> {
+    let items: Vec<_> = iter_trash_items()?.filter(|item| tags.is_empty() || has_tags(item, tags)).collect();
+    let total: u64 = items.iter().filter_map(|item| item.size).sum();
+    match tags.is_empty() {
+        true => println!("rip would permanently delete all {} items ({}) from:", items.len(), human_size(total)),
+        false => println!("rip would permanently delete {} items tagged {} ({}) from:", items.len(), tags.join(", "), human_size(total)),
+    }
+    for trash in trash_roots()? {
+        let count = items.iter().filter(|item| item.trash_dir() == trash).count();
+        println!("  {} ({count} items)", trash.display());
+    }
+    hook_line("pre_empty");
+    hook_line("post_empty");
+    if shred {
+        println!("  file contents are overwritten first (best-effort on SSDs and copy-on-write filesystems)");
+    }
+    println!("There is no prompt and no undo.");
+    println!("Nothing was changed.");
+    Ok(())
+}
+
+pub fn explain_purge(n: usize, shred: bool) -> Result<()> {
+    let item = nth_item(n)?;
+    println!("rip would permanently delete item #{n}: {}", item.original_path.display());
+    println!("  trashed:  {}", item.deletion_date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"));
+    if let Some(size) = item.size {
+        println!("  size:     {}", human_size(size));
+    }
+    println!("  location: {}", item.trashed_path().display());
+    hook_line("pre_purge");
+    hook_line("post_purge");
+    if shred {
+        println!("  file contents are overwritten first (best-effort on SSDs and copy-on-write filesystems)");
+    }
+    println!("There is no prompt and no undo.");
+    println!("Nothing was changed.");
+    Ok(())
+}
+
+pub fn explain_purge_matching(filter: &ItemFilter, shred: bool) -> Result<()> {
+    let items: Vec<_> = iter_trash_items()?.filter(|item| filter.matches(item)).collect();
+    let total: u64 = items.iter().filter_map(|item| item.size).sum();
+    println!("rip would list {} items {filter} ({}) and, once confirmed, permanently delete them:", items.len(), human_size(total));
+    for item in &items {
+        println!("  {}", item.original_path.display());
+    }
+    hook_line("pre_purge");
+    hook_line("post_purge");
+    if shred {
+        println!("  file contents are overwritten first (best-effort on SSDs and copy-on-write filesystems)");
+    }
+    println!("Nothing was changed.");
+    Ok(())
+}
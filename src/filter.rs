@@ -0,0 +1,62 @@
+// src/filter.rs - Item filters (original path glob, age, tags, deleting user) shared by the bulk trash commands
+use crate::duration::Span;
+use crate::sandbox;
+use crate::sudo;
+use crate::trash::{has_tags, TrashItem};
+use anyhow::{anyhow, Context, Result};
+use glob::Pattern;
+use std::fmt;
+
+#[derive(Default)]
+pub struct ItemFilter {
+    glob: Option<(Pattern, Option<Pattern>)>,
+    older_than: Option<Span>,
+    tags: Vec<String>,
+    trashed_by: Option<u32>,
+}
+
+impl ItemFilter {
+    pub fn new(glob: Option<&str>, older_than: Option<Span>, tags: &[String], user: Option<&str>) -> Result<Self> {
+        let trashed_by = user.map(|name| sudo::user_id(name).ok_or_else(|| anyhow!("No such user: {name}"))).transpose()?;
+        let glob = glob
+            .map(|glob| -> Result<_> {
+                let pattern = Pattern::new(glob).with_context(|| format!("Invalid pattern {glob:?}"))?;
+                let dir = glob.strip_suffix("/**").map(Pattern::new).transpose()?;
+                Ok((pattern, dir))
+            })
+            .transpose()?;
+        Ok(ItemFilter { glob, older_than, tags: tags.to_vec(), trashed_by })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.glob.is_none() && self.older_than.is_none() && self.tags.is_empty() && self.trashed_by.is_none()
+    }
+
+    pub fn matches(&self, item: &TrashItem) -> bool {
+        let path_matches = self.glob.as_ref().is_none_or(|(pattern, dir)| {
+            pattern.matches_path(&item.original_path) || dir.as_ref().is_some_and(|dir| dir.matches_path(&item.original_path))
+        });
+        let old_enough = self.older_than.is_none_or(|span| item.deletion_date < span.before(sandbox::now()));
+        let by_user = self.trashed_by.is_none_or(|uid| item.trashed_by == Some(uid));
+        path_matches && old_enough && by_user && has_tags(item, &self.tags)
+    }
+}
+
+impl fmt::Display for ItemFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some((pattern, _)) = &self.glob {
+            parts.push(format!("matching {pattern}"));
+        }
+        if let Some(span) = self.older_than {
+            parts.push(format!("trashed more than {span} ago"));
+        }
+        if !self.tags.is_empty() {
+            parts.push(format!("tagged {}", self.tags.join(", ")));
+        }
+        if let Some(uid) = self.trashed_by {
+            parts.push(format!("trashed by {}", sudo::user_name(uid)));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
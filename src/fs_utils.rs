@@ -1,29 +1,534 @@
-// src/fs_utils.rs - Helper functions for recursive copy and remove
-use anyhow::Result;
-use std::fs;
+// src/fs_utils.rs - Helper functions for recursive copy, remove and size accounting
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use std::ffi::{CStr, CString};
+use std::os::unix::ffi::OsStrExt;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
 use std::path::Path;
-use walkdir::WalkDir;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::warn;
+use xattr::FileExt;
 
 pub fn copy_recursively(src: &Path, dst: &Path) -> Result<()> {
-    fs::create_dir_all(dst)?;
-    for entry in WalkDir::new(src).into_iter().filter_map(|e| e.ok()) {
-        let src_path = entry.path();
-        let rel = src_path.strip_prefix(src)?;
-        let dst_path = dst.join(rel);
-        if src_path.is_dir() {
-            fs::create_dir_all(&dst_path)?;
-        } else {
-            fs::copy(src_path, &dst_path)?;
+    let (src_parent, src_name) = open_parent(src)?;
+    let (dst_parent, dst_name) = open_parent(dst)?;
+    let mut failures = 0;
+    copy_dir_at(&src_parent, &src_name, &dst_parent, &dst_name, src, &mut failures)
+        .with_context(|| format!("{}", src.display()))?;
+    if failures > 0 {
+        return Err(anyhow!("{failures} entries could not be copied"));
+    }
+    Ok(())
+}
+
+fn copy_dir_at(src_parent: &OwnedFd, src_name: &CStr, dst_parent: &OwnedFd, dst_name: &CStr, shown: &Path, failures: &mut usize) -> io::Result<()> {
+    let st = stat_at(src_parent, src_name)?;
+    if unsafe { libc::mkdirat(dst_parent.as_raw_fd(), dst_name.as_ptr(), 0o700) } != 0 {
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::AlreadyExists {
+            return Err(e);
+        }
+    }
+    let src_dir = open_at(src_parent, src_name, libc::O_RDONLY | libc::O_DIRECTORY)?;
+    let dst_dir = open_at(dst_parent, dst_name, libc::O_RDONLY | libc::O_DIRECTORY)?;
+    for name in read_names(&src_dir)? {
+        let shown = shown.join(std::ffi::OsStr::from_bytes(name.to_bytes()));
+        if let Err(e) = copy_entry_at(&src_dir, &dst_dir, &name, &shown, failures) {
+            warn!("{}: {e}", shown.display());
+            *failures += 1;
+        }
+    }
+    let (src_dir, dst_dir) = (fs::File::from(src_dir), fs::File::from(dst_dir));
+    preserve_metadata_fd(&st, &src_dir, &dst_dir);
+    dst_dir.set_permissions(fs::Permissions::from_mode(st.st_mode & 0o7777))
+}
+
+fn copy_entry_at(src_dir: &OwnedFd, dst_dir: &OwnedFd, name: &CStr, shown: &Path, failures: &mut usize) -> io::Result<()> {
+    let st = stat_at(src_dir, name)?;
+    match st.st_mode & libc::S_IFMT {
+        libc::S_IFDIR => copy_dir_at(src_dir, name, dst_dir, name, shown, failures),
+        libc::S_IFLNK => {
+            let mut target = vec![0u8; st.st_size as usize + 1];
+            let len = unsafe { libc::readlinkat(src_dir.as_raw_fd(), name.as_ptr(), target.as_mut_ptr().cast(), target.len()) };
+            if len < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            target.truncate(len as usize);
+            let target = CString::new(target)?;
+            if unsafe { libc::symlinkat(target.as_ptr(), dst_dir.as_raw_fd(), name.as_ptr()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if unsafe { libc::geteuid() } == 0 {
+                unsafe { libc::fchownat(dst_dir.as_raw_fd(), name.as_ptr(), st.st_uid, st.st_gid, libc::AT_SYMLINK_NOFOLLOW) };
+            }
+            Ok(())
+        }
+        libc::S_IFREG => {
+            let mut src = fs::File::from(open_at(src_dir, name, libc::O_RDONLY)?);
+            let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL;
+            let fd = unsafe { libc::openat(dst_dir.as_raw_fd(), name.as_ptr(), flags | libc::O_CLOEXEC, 0o600) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut dst = unsafe { fs::File::from_raw_fd(fd) };
+            io::copy(&mut src, &mut dst)?;
+            preserve_metadata_fd(&st, &src, &dst);
+            dst.set_permissions(fs::Permissions::from_mode(st.st_mode & 0o7777))
+        }
+        _ => match unsafe { libc::mknodat(dst_dir.as_raw_fd(), name.as_ptr(), st.st_mode, st.st_rdev) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        },
+    }
+}
+
+fn preserve_metadata_fd(st: &libc::stat, src: &fs::File, dst: &fs::File) {
+    if unsafe { libc::geteuid() } == 0 {
+        unsafe { libc::fchown(dst.as_raw_fd(), st.st_uid, st.st_gid) };
+    }
+    let times = [
+        libc::timespec { tv_sec: st.st_atime, tv_nsec: st.st_atime_nsec },
+        libc::timespec { tv_sec: st.st_mtime, tv_nsec: st.st_mtime_nsec },
+    ];
+    if unsafe { libc::futimens(dst.as_raw_fd(), times.as_ptr()) } != 0 {
+        warn!("cannot keep the timestamps: {}", io::Error::last_os_error());
+    }
+    let Ok(names) = src.list_xattr() else { return };
+    for name in names {
+        if let Ok(Some(value)) = src.get_xattr(&name) {
+            let _ = dst.set_xattr(&name, &value);
         }
     }
+}
+
+pub fn move_across(src: &Path, dst: &Path) -> Result<()> {
+    copy_verified(src, dst)?;
+    remove_recursively(src)
+}
+
+pub fn copy_verified(src: &Path, dst: &Path) -> Result<()> {
+    let meta = src.symlink_metadata()?;
+    let copied = if meta.file_type().is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(src)?, dst).map_err(Into::into)
+    } else if meta.is_dir() {
+        copy_recursively(src, dst)
+    } else {
+        fs::copy(src, dst).and_then(|_| set_times(dst, &meta)).map_err(Into::into)
+    };
+    let verified = copied.and_then(|()| {
+        preserve_metadata(src, dst);
+        let (expected, actual) = (path_size(src)?, path_size(dst)?);
+        match expected == actual {
+            true => Ok(()),
+            false => Err(anyhow!("copy holds {} instead of {}", human_size(actual), human_size(expected))),
+        }
+    });
+    if let Err(e) = verified {
+        let _ = remove_recursively(dst);
+        return Err(e);
+    }
     Ok(())
 }
 
+fn set_times(path: &Path, meta: &fs::Metadata) -> io::Result<()> {
+    let times = [
+        libc::timespec { tv_sec: meta.atime(), tv_nsec: meta.atime_nsec() },
+        libc::timespec { tv_sec: meta.mtime(), tv_nsec: meta.mtime_nsec() },
+    ];
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    match unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+pub fn preserve_metadata(src: &Path, dst: &Path) {
+    if unsafe { libc::geteuid() } == 0 {
+        if let Ok(meta) = src.symlink_metadata() {
+            let _ = std::os::unix::fs::lchown(dst, Some(meta.uid()), Some(meta.gid()));
+        }
+    }
+    let Ok(names) = xattr::list(src) else { return };
+    for name in names {
+        if let Ok(Some(value)) = xattr::get(src, &name) {
+            let _ = xattr::set(dst, &name, &value);
+        }
+    }
+}
+
 pub fn remove_recursively(path: &Path) -> Result<()> {
-    if path.is_dir() {
-        fs::remove_dir_all(path)
+    let (parent, name) = open_parent(path)?;
+    remove_at(&parent, &name).with_context(|| format!("{}", path.display()))
+}
+
+fn remove_at(parent: &OwnedFd, name: &CStr) -> io::Result<()> {
+    let st = stat_at(parent, name)?;
+    if st.st_mode & libc::S_IFMT != libc::S_IFDIR {
+        return unlink_at(parent, name, 0);
+    }
+    let mode = st.st_mode & 0o7777 | 0o700;
+    let flags = libc::O_RDONLY | libc::O_DIRECTORY;
+    let dir = match open_at(parent, name, flags) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            unsafe { libc::fchmodat(parent.as_raw_fd(), name.as_ptr(), mode, 0) };
+            open_at(parent, name, flags)?
+        }
+        result => result?,
+    };
+    if st.st_mode & 0o700 != 0o700 {
+        unsafe { libc::fchmod(dir.as_raw_fd(), mode) };
+    }
+    for child in read_names(&dir)? {
+        remove_at(&dir, &child)?;
+    }
+    drop(dir);
+    unlink_at(parent, name, libc::AT_REMOVEDIR)
+}
+
+pub fn path_size(path: &Path) -> Result<u64> {
+    let metadata = path.symlink_metadata()?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let (parent, name) = open_parent(path)?;
+    Ok(dir_size_at(&open_at(&parent, &name, libc::O_RDONLY | libc::O_DIRECTORY)?)?)
+}
+
+fn dir_size_at(dir: &OwnedFd) -> io::Result<u64> {
+    let mut total = 0;
+    for name in read_names(dir)? {
+        let st = stat_at(dir, &name)?;
+        total += match st.st_mode & libc::S_IFMT {
+            libc::S_IFDIR => dir_size_at(&open_at(dir, &name, libc::O_RDONLY | libc::O_DIRECTORY)?)?,
+            _ => st.st_size as u64,
+        };
+    }
+    Ok(total)
+}
+
+static CASE_INSENSITIVE: Mutex<Option<HashMap<u64, bool>>> = Mutex::new(None);
+
+pub fn case_insensitive(dir: &Path) -> bool {
+    let Ok(meta) = dir.metadata() else { return false };
+    let mut cache = CASE_INSENSITIVE.lock().unwrap_or_else(|e| e.into_inner());
+    *cache.get_or_insert_with(HashMap::new).entry(meta.dev()).or_insert_with(|| probe_case_insensitive(dir, &meta))
+}
+
+fn probe_case_insensitive(dir: &Path, meta: &fs::Metadata) -> bool {
+    let swapped = |name: &str| name.chars().map(|c| match c.is_ascii_lowercase() {
+        true => c.to_ascii_uppercase(),
+        false => c.to_ascii_lowercase(),
+    }).collect::<String>();
+    if let Some(name) = dir.file_name().and_then(|n| n.to_str()).filter(|n| n.bytes().any(|b| b.is_ascii_alphabetic())) {
+        return dir.with_file_name(swapped(name)).metadata().is_ok_and(|m| (m.dev(), m.ino()) == (meta.dev(), meta.ino()));
+    }
+    let probe = dir.join(format!(".rip-case-{}", std::process::id()));
+    if OpenOptions::new().write(true).create_new(true).open(&probe).is_err() {
+        return false;
+    }
+    let folded = probe.with_file_name(swapped(&probe.file_name().unwrap_or_default().to_string_lossy()));
+    let insensitive = folded.symlink_metadata().is_ok();
+    let _ = fs::remove_file(&probe);
+    insensitive
+}
+
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes}B")
     } else {
-        fs::remove_file(path)
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
+
+pub fn parse_size(text: &str) -> Result<u64> {
+    let s = text.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number.parse().map_err(|_| anyhow!("Invalid size: {text} (try 500M, 5G)"))?;
+    let multiplier: u64 = match unit.trim().to_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(anyhow!("Invalid size unit in '{text}' (use K, M, G or T)")),
+    };
+    Ok((value * multiplier as f64) as u64)
+}
+
+pub struct DiskUsage {
+    pub total: u64,
+    pub available: u64,
+}
+
+impl DiskUsage {
+    pub fn used_percent(&self, freed: u64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let used = (self.total - self.available).saturating_sub(freed);
+        used as f64 * 100.0 / self.total as f64
     }
-    .map_err(Into::into)
-}
\ No newline at end of file
+}
+
+pub fn disk_usage(path: &Path) -> Result<DiskUsage> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(anyhow!("statvfs {}: {}", path.display(), std::io::Error::last_os_error()));
+    }
+    let block = stat.f_frsize as u64;
+    Ok(DiskUsage {
+        total: stat.f_blocks as u64 * block,
+        available: stat.f_bavail as u64 * block,
+    })
+}
+
+const SHRED_PASSES: usize = 3;
+
+fn shred_file(mut file: fs::File) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    let mut buf = vec![0u8; 64 * 1024];
+    for _ in 0..SHRED_PASSES {
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            OsRng.fill_bytes(&mut buf);
+            let n = remaining.min(buf.len() as u64) as usize;
+            file.write_all(&buf[..n])?;
+            remaining -= n as u64;
+        }
+        file.sync_all()?;
+    }
+    file.set_len(0)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+pub fn shred_path(path: &Path) -> Result<()> {
+    let (parent, name) = open_parent(path)?;
+    shred_at(&parent, &name).with_context(|| format!("{}", path.display()))
+}
+
+fn shred_at(parent: &OwnedFd, name: &CStr) -> io::Result<()> {
+    let st = stat_at(parent, name)?;
+    match st.st_mode & libc::S_IFMT {
+        libc::S_IFDIR => {
+            let dir = open_at(parent, name, libc::O_RDONLY | libc::O_DIRECTORY)?;
+            read_names(&dir)?.iter().try_for_each(|child| shred_at(&dir, child))
+        }
+        libc::S_IFREG if st.st_nlink == 1 => {
+            let file = match open_at(parent, name, libc::O_WRONLY) {
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                    unsafe { libc::fchmodat(parent.as_raw_fd(), name.as_ptr(), st.st_mode & 0o7777 | 0o200, 0) };
+                    open_at(parent, name, libc::O_WRONLY)?
+                }
+                result => result?,
+            };
+            shred_file(fs::File::from(file))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn open_parent(path: &Path) -> io::Result<(OwnedFd, CString)> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let dir = OpenOptions::new().read(true).custom_flags(libc::O_DIRECTORY).open(parent)?;
+    Ok((dir.into(), CString::new(name.as_bytes())?))
+}
+
+fn stat_at(dir: &OwnedFd, name: &CStr) -> io::Result<libc::stat> {
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstatat(dir.as_raw_fd(), name.as_ptr(), &mut st, libc::AT_SYMLINK_NOFOLLOW) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(st)
+}
+
+fn identity_at(dir: &OwnedFd, name: &CStr) -> io::Result<(u64, u64)> {
+    stat_at(dir, name).map(|st| (st.st_dev, st.st_ino))
+}
+
+fn open_at(dir: &OwnedFd, name: &CStr, flags: i32) -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), flags | libc::O_NOFOLLOW | libc::O_CLOEXEC) };
+    match fd {
+        -1 => Err(io::Error::last_os_error()),
+        fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+    }
+}
+
+fn read_names(dir: &OwnedFd) -> io::Result<Vec<CString>> {
+    let fd = unsafe { libc::dup(dir.as_raw_fd()) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stream = unsafe { libc::fdopendir(fd) };
+    if stream.is_null() {
+        let e = io::Error::last_os_error();
+        unsafe { libc::close(fd) };
+        return Err(e);
+    }
+    unsafe { libc::rewinddir(stream) };
+    let mut names = Vec::new();
+    loop {
+        let entry = unsafe { libc::readdir(stream) };
+        if entry.is_null() {
+            break;
+        }
+        let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+        if name.to_bytes() != b"." && name.to_bytes() != b".." {
+            names.push(name.to_owned());
+        }
+    }
+    unsafe { libc::closedir(stream) };
+    Ok(names)
+}
+
+fn unlink_at(dir: &OwnedFd, name: &CStr, flags: i32) -> io::Result<()> {
+    match unsafe { libc::unlinkat(dir.as_raw_fd(), name.as_ptr(), flags) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn rename_at(from_dir: &OwnedFd, from: &CStr, to_dir: &OwnedFd, to: &CStr) -> io::Result<()> {
+    let (from_fd, to_fd) = (from_dir.as_raw_fd(), to_dir.as_raw_fd());
+    let mut result = unsafe { libc::renameat2(from_fd, from.as_ptr(), to_fd, to.as_ptr(), libc::RENAME_NOREPLACE) };
+    if result != 0 && io::Error::last_os_error().raw_os_error() == Some(libc::EINVAL) {
+        result = unsafe { libc::renameat(from_fd, from.as_ptr(), to_fd, to.as_ptr()) };
+    }
+    match result {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+pub fn rename_inspected(src: &Path, inspected: &fs::Metadata, dst: &Path) -> io::Result<()> {
+    let (src_dir, src_name) = open_parent(src)?;
+    let (dst_dir, dst_name) = open_parent(dst)?;
+    let expected = (inspected.dev(), inspected.ino());
+    if identity_at(&src_dir, &src_name)? != expected {
+        return Err(io::Error::other("replaced by another file after it was inspected; left in place"));
+    }
+    rename_at(&src_dir, &src_name, &dst_dir, &dst_name)?;
+    if identity_at(&dst_dir, &dst_name)? != expected {
+        let _ = rename_at(&dst_dir, &dst_name, &src_dir, &src_name);
+        return Err(io::Error::other("replaced by another file while it was being moved; left in place"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(tag: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rip-test-{}-{tag}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn copies_link_loops_and_broken_links_as_links() {
+        let tmp = TempDir::new("link-loops");
+        let (src, dst, outside) = (tmp.0.join("project"), tmp.0.join("trashed"), tmp.0.join("outside"));
+        fs::create_dir_all(src.join("nested")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep.txt"), "not mine").unwrap();
+        fs::write(src.join("nested/data.txt"), "payload").unwrap();
+        symlink("self", src.join("self")).unwrap();
+        symlink("..", src.join("nested/up")).unwrap();
+        symlink("does/not/exist", src.join("broken")).unwrap();
+        symlink(&outside, src.join("elsewhere")).unwrap();
+
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        fs::File::open(src.join("nested/data.txt")).unwrap().set_modified(old).unwrap();
+        fs::File::open(src.join("nested")).unwrap().set_modified(old).unwrap();
+        let mtimes: HashMap<_, _> = ["nested/data.txt", "nested", "."].map(|entry| (entry, src.join(entry).metadata().unwrap().mtime())).into();
+
+        let size = path_size(&src).unwrap();
+        move_across(&src, &dst).unwrap();
+
+        for (link, target) in [("self", Path::new("self")), ("nested/up", Path::new("..")), ("broken", Path::new("does/not/exist")), ("elsewhere", outside.as_path())] {
+            assert!(dst.join(link).symlink_metadata().unwrap().file_type().is_symlink(), "{link} is not a symlink");
+            assert_eq!(fs::read_link(dst.join(link)).unwrap(), target);
+        }
+        assert_eq!(fs::read_to_string(dst.join("nested/data.txt")).unwrap(), "payload");
+        for entry in ["nested/data.txt", "nested", "."] {
+            assert_eq!(dst.join(entry).metadata().unwrap().mtime(), mtimes[entry], "{entry} lost its mtime");
+        }
+        assert_eq!(path_size(&dst).unwrap(), size);
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(outside.join("keep.txt")).unwrap(), "not mine");
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn copies_read_only_files_with_their_timestamps() {
+        let tmp = TempDir::new("read-only-copy");
+        let (src, dst) = (tmp.0.join("frozen.txt"), tmp.0.join("copied.txt"));
+        fs::write(&src, "frozen").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        fs::File::options().write(true).open(&src).unwrap().set_modified(old).unwrap();
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o444)).unwrap();
+
+        copy_verified(&src, &dst).unwrap();
+
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "frozen");
+        assert_eq!(dst.metadata().unwrap().mode() & 0o777, 0o444);
+        assert_eq!(dst.metadata().unwrap().modified().unwrap(), old);
+    }
+
+    #[test]
+    fn shreds_and_removes_locked_trees_without_following_links() {
+        let tmp = TempDir::new("locked-tree");
+        let (tree, outside) = (tmp.0.join("tree"), tmp.0.join("outside"));
+        fs::create_dir_all(tree.join("locked/deeper")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep.txt"), "not mine").unwrap();
+        fs::write(tree.join("locked/deeper/secret.txt"), "payload").unwrap();
+        fs::write(tree.join("locked/object"), "read-only payload").unwrap();
+        fs::set_permissions(tree.join("locked/object"), fs::Permissions::from_mode(0o444)).unwrap();
+        symlink(&outside, tree.join("locked/elsewhere")).unwrap();
+        symlink(outside.join("keep.txt"), tree.join("keep-link")).unwrap();
+
+        shred_path(&tree).unwrap();
+        assert_eq!(fs::read(tree.join("locked/deeper/secret.txt")).unwrap(), b"");
+        assert_eq!(fs::read(tree.join("locked/object")).unwrap(), b"");
+        fs::set_permissions(tree.join("locked/deeper"), fs::Permissions::from_mode(0o500)).unwrap();
+        fs::set_permissions(tree.join("locked"), fs::Permissions::from_mode(0o100)).unwrap();
+        remove_recursively(&tree).unwrap();
+
+        assert!(tree.symlink_metadata().is_err());
+        assert_eq!(fs::read_to_string(outside.join("keep.txt")).unwrap(), "not mine");
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,273 @@
+// src/fuse.rs - Read-only FUSE view of the trash, organized by original path and deletion date
+use anyhow::Result;
+use std::path::Path;
+
+#[cfg(feature = "fuse")]
+pub fn mount_trash(mountpoint: &Path) -> Result<()> {
+    use anyhow::Context;
+    use fuser::MountOption;
+
+    let items = crate::trash::load_trash_items()?;
+    let view = view::TrashView::build(&items);
+    println!("Trash mounted read-only at {} (unmount with: fusermount -u {0})", mountpoint.display());
+    fuser::mount2(view, mountpoint, &[MountOption::RO, MountOption::FSName("rip".to_owned()), MountOption::NoExec])
+        .with_context(|| format!("Cannot mount the trash at {}", mountpoint.display()))
+}
+
+#[cfg(not(feature = "fuse"))]
+pub fn mount_trash(_mountpoint: &Path) -> Result<()> {
+    Err(anyhow::anyhow!("rip was built without FUSE support (rebuild with --features fuse)"))
+}
+
+#[cfg(feature = "fuse")]
+mod view {
+    use crate::trash::TrashItem;
+    use chrono::Local;
+    use fuser::{FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry, Request};
+    use std::collections::{BTreeMap, HashMap};
+    use std::ffi::{OsStr, OsString};
+    use std::fs::{self, File};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{FileExt, MetadataExt};
+    use std::path::{Component, PathBuf};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    const TTL: Duration = Duration::from_secs(60);
+    const ROOT: u64 = 1;
+
+    enum Node {
+        Dir { parent: u64, children: BTreeMap<OsString, u64> },
+        Real { parent: u64, path: PathBuf },
+    }
+
+    pub struct TrashView {
+        nodes: Vec<Node>,
+        real: HashMap<PathBuf, u64>,
+        mounted_at: SystemTime,
+        uid: u32,
+        gid: u32,
+    }
+
+    impl TrashView {
+        pub fn build(items: &[TrashItem]) -> Self {
+            let mut view = TrashView {
+                nodes: vec![Node::Dir { parent: ROOT, children: BTreeMap::new() }],
+                real: HashMap::new(),
+                mounted_at: SystemTime::now(),
+                uid: unsafe { libc::getuid() },
+                gid: unsafe { libc::getgid() },
+            };
+            let by_path = view.dir(ROOT, OsStr::new("by-path"));
+            let by_date = view.dir(ROOT, OsStr::new("by-date"));
+            let items: Vec<_> = items.iter().filter(|i| i.compressed.is_none() && i.encrypted.is_none()).collect();
+            let parents: Vec<u64> = items
+                .iter()
+                .map(|item| {
+                    let mut dir = by_path;
+                    for component in item.original_path.parent().into_iter().flat_map(|p| p.components()) {
+                        if let Component::Normal(part) = component {
+                            dir = view.dir(dir, part);
+                        }
+                    }
+                    dir
+                })
+                .collect();
+            for (item, dir) in items.into_iter().zip(parents) {
+                let date = item.deletion_date.with_timezone(&Local);
+                let name = item
+                    .original_path
+                    .file_name()
+                    .map(OsStr::to_os_string)
+                    .unwrap_or_else(|| OsString::from(&item.trashed_name));
+                view.place(dir, &name, &date.format("%Y-%m-%d %H:%M:%S").to_string(), item);
+
+                let day = view.dir(by_date, OsStr::new(&date.format("%Y-%m-%d").to_string()));
+                view.place(day, &name, &date.format("%H:%M:%S").to_string(), item);
+            }
+            view
+        }
+
+        fn push(&mut self, node: Node) -> u64 {
+            self.nodes.push(node);
+            self.nodes.len() as u64
+        }
+
+        fn dir(&mut self, parent: u64, name: &OsStr) -> u64 {
+            if let Some(Node::Dir { children, .. }) = self.node(parent) {
+                if let Some(&ino) = children.get(name) {
+                    return ino;
+                }
+            }
+            let ino = self.push(Node::Dir { parent, children: BTreeMap::new() });
+            self.link(parent, name.to_os_string(), ino);
+            ino
+        }
+
+        fn place(&mut self, parent: u64, name: &OsStr, stamp: &str, item: &TrashItem) {
+            let mut stamped = name.to_os_string();
+            stamped.push(format!(" ({stamp})"));
+            let candidates = [name.to_os_string(), stamped, OsString::from(&item.trashed_name)];
+            let Some(Node::Dir { children, .. }) = self.node(parent) else { return };
+            let Some(leaf) = candidates.into_iter().find(|c| !children.contains_key(c)) else { return };
+            let ino = self.push(Node::Real { parent, path: item.trashed_path() });
+            self.link(parent, leaf, ino);
+        }
+
+        fn link(&mut self, parent: u64, name: OsString, ino: u64) {
+            if let Some(Node::Dir { children, .. }) = self.nodes.get_mut(parent as usize - 1) {
+                children.insert(name, ino);
+            }
+        }
+
+        fn node(&self, ino: u64) -> Option<&Node> {
+            self.nodes.get((ino as usize).checked_sub(1)?)
+        }
+
+        fn real_child(&mut self, parent: u64, path: PathBuf) -> u64 {
+            if let Some(&ino) = self.real.get(&path) {
+                return ino;
+            }
+            let ino = self.push(Node::Real { parent, path: path.clone() });
+            self.real.insert(path, ino);
+            ino
+        }
+
+        fn children(&mut self, ino: u64) -> Option<Vec<(OsString, u64)>> {
+            match self.node(ino)? {
+                Node::Dir { children, .. } => Some(children.iter().map(|(n, i)| (n.clone(), *i)).collect()),
+                Node::Real { path, .. } => {
+                    let mut entries: Vec<_> = fs::read_dir(path).ok()?.flatten().map(|e| (e.file_name(), e.path())).collect();
+                    entries.sort();
+                    Some(entries.into_iter().map(|(name, path)| (name, self.real_child(ino, path))).collect())
+                }
+            }
+        }
+
+        fn attr(&self, ino: u64) -> Option<FileAttr> {
+            let base = FileAttr {
+                ino,
+                size: 0,
+                blocks: 0,
+                atime: self.mounted_at,
+                mtime: self.mounted_at,
+                ctime: self.mounted_at,
+                crtime: self.mounted_at,
+                kind: FileType::Directory,
+                perm: 0o555,
+                nlink: 2,
+                uid: self.uid,
+                gid: self.gid,
+                rdev: 0,
+                blksize: 4096,
+                flags: 0,
+            };
+            match self.node(ino)? {
+                Node::Dir { .. } => Some(base),
+                Node::Real { path, .. } => {
+                    let meta = path.symlink_metadata().ok()?;
+                    let time = |secs: i64| UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64);
+                    let kind = if meta.is_dir() {
+                        FileType::Directory
+                    } else if meta.file_type().is_symlink() {
+                        FileType::Symlink
+                    } else {
+                        FileType::RegularFile
+                    };
+                    Some(FileAttr {
+                        size: meta.len(),
+                        blocks: meta.blocks(),
+                        atime: time(meta.atime()),
+                        mtime: time(meta.mtime()),
+                        ctime: time(meta.ctime()),
+                        kind,
+                        perm: (meta.mode() & 0o7555) as u16,
+                        nlink: meta.nlink() as u32,
+                        uid: meta.uid(),
+                        gid: meta.gid(),
+                        ..base
+                    })
+                }
+            }
+        }
+
+        fn parent(&self, ino: u64) -> u64 {
+            match self.node(ino) {
+                Some(Node::Dir { parent, .. } | Node::Real { parent, .. }) => *parent,
+                None => ROOT,
+            }
+        }
+
+        fn real_path(&self, ino: u64) -> Option<&PathBuf> {
+            match self.node(ino)? {
+                Node::Real { path, .. } => Some(path),
+                Node::Dir { .. } => None,
+            }
+        }
+    }
+
+    impl Filesystem for TrashView {
+        fn lookup(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+            let found = self
+                .children(parent)
+                .and_then(|children| children.into_iter().find(|(n, _)| n == name))
+                .and_then(|(_, ino)| self.attr(ino));
+            match found {
+                Some(attr) => reply.entry(&TTL, &attr, 0),
+                None => reply.error(libc::ENOENT),
+            }
+        }
+
+        fn getattr(&mut self, _req: &Request<'_>, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
+            match self.attr(ino) {
+                Some(attr) => reply.attr(&TTL, &attr),
+                None => reply.error(libc::ENOENT),
+            }
+        }
+
+        fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+            match self.real_path(ino).and_then(|p| fs::read_link(p).ok()) {
+                Some(target) => reply.data(target.as_os_str().as_bytes()),
+                None => reply.error(libc::EINVAL),
+            }
+        }
+
+        fn read(
+            &mut self,
+            _req: &Request<'_>,
+            ino: u64,
+            _fh: u64,
+            offset: i64,
+            size: u32,
+            _flags: i32,
+            _lock_owner: Option<u64>,
+            reply: ReplyData,
+        ) {
+            let Some(path) = self.real_path(ino) else {
+                return reply.error(libc::EISDIR);
+            };
+            let mut buf = vec![0; size as usize];
+            match File::open(path).and_then(|f| f.read_at(&mut buf, offset.max(0) as u64)) {
+                Ok(n) => reply.data(&buf[..n]),
+                Err(e) => reply.error(e.raw_os_error().unwrap_or(libc::EIO)),
+            }
+        }
+
+        fn readdir(&mut self, _req: &Request<'_>, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+            let Some(children) = self.children(ino) else {
+                return reply.error(libc::ENOTDIR);
+            };
+            let parent = self.parent(ino);
+            let mut entries = vec![(ino, FileType::Directory, OsString::from(".")), (parent, FileType::Directory, OsString::from(".."))];
+            for (name, child) in children {
+                let kind = self.attr(child).map_or(FileType::RegularFile, |a| a.kind);
+                entries.push((child, kind, name));
+            }
+            for (i, (child, kind, name)) in entries.into_iter().enumerate().skip(offset.max(0) as usize) {
+                if reply.add(child, i as i64 + 1, kind, name) {
+                    break;
+                }
+            }
+            reply.ok();
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// src/git.rs - Git working tree helpers: unsaved work checks and untracked file listing
+use crate::trash::confirm;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+use walkdir::WalkDir;
+
+fn repositories(path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git" || e.depth() == 0)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir() && e.path().join(".git").exists())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn git_output(repo: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn unsaved_work(repo: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    let changed = git_output(repo, &["status", "--porcelain"]).map_or(0, |out| out.lines().count());
+    if changed > 0 {
+        problems.push(format!("{changed} uncommitted changes"));
+    }
+    let unpushed = git_output(repo, &["log", "--branches", "--not", "--remotes", "--oneline"])
+        .map_or(0, |out| out.lines().count());
+    if unpushed > 0 {
+        problems.push(format!("{unpushed} unpushed commits"));
+    }
+    problems
+}
+
+pub fn unsaved_repositories(path: &Path) -> Vec<(PathBuf, Vec<String>)> {
+    if !path.symlink_metadata().is_ok_and(|m| m.is_dir()) {
+        return Vec::new();
+    }
+    repositories(path)
+        .into_iter()
+        .map(|repo| {
+            let problems = unsaved_work(&repo);
+            debug!("git repository {}: {problems:?}", repo.display());
+            (repo, problems)
+        })
+        .filter(|(_, problems)| !problems.is_empty())
+        .collect()
+}
+
+pub fn confirm_repositories(path: &Path) -> bool {
+    let unsaved = unsaved_repositories(path);
+    for (repo, problems) in &unsaved {
+        println!("Warning: {} is a git repository with {}", repo.display(), problems.join(" and "));
+    }
+    unsaved.is_empty() || confirm(&format!("Trash {} anyway? [y/N] ", path.display()))
+}
+
+pub fn untracked_paths(dir: &Path, ignored: bool, directories: bool) -> Result<Vec<String>> {
+    let mut git = Command::new("git");
+    git.arg("-C").arg(dir).args(["ls-files", "-z", "--others", "--exclude-standard", "--directory"]);
+    if ignored {
+        git.arg("--ignored");
+    }
+    let output = git.output().context("Cannot run git")?;
+    if !output.status.success() {
+        return Err(anyhow!("{}: {}", dir.display(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|p| !p.is_empty() && (directories || !p.ends_with('/')))
+        .map(|p| match dir == Path::new(".") {
+            true => p.trim_end_matches('/').to_owned(),
+            false => dir.join(p.trim_end_matches('/')).to_string_lossy().into_owned(),
+        })
+        .collect())
+}
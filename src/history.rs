@@ -0,0 +1,133 @@
+// src/history.rs - Grouped trash operations: one record per invocation for --history and --undo
+use crate::i18n::tr;
+use crate::notify::notify_trashed;
+use crate::sandbox;
+use crate::trash::{iter_trash_items, restore_each, state_dir, trash_order, RestoreOutput};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use urlencoding::{decode_binary, encode_binary};
+
+const HISTORY_FILE: &str = "history";
+const HISTORY_SHOWN: usize = 20;
+
+pub struct Operation {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub trashed: usize,
+    pub failed: usize,
+    pub from: PathBuf,
+}
+
+impl Operation {
+    pub fn start() -> Self {
+        Operation { id: nanoid::nanoid!(10), time: sandbox::now(), trashed: 0, failed: 0, from: PathBuf::new() }
+    }
+
+    pub fn finish(mut self, targets: &[PathBuf]) {
+        if self.trashed == 0 && self.failed == 0 {
+            return;
+        }
+        self.from = common_parent(targets);
+        notify_trashed(self.trashed, &self.from);
+        let line = format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            self.id,
+            self.time.to_rfc3339(),
+            self.trashed,
+            self.failed,
+            encode_binary(self.from.as_os_str().as_bytes())
+        );
+        let written = state_dir().and_then(|dir| {
+            OpenOptions::new().create(true).append(true).open(dir.join(HISTORY_FILE))?.write_all(line.as_bytes())?;
+            Ok(())
+        });
+        if let Err(e) = written {
+            warn!("cannot record the operation in the history: {e}");
+        }
+    }
+
+    fn describe(&self) -> String {
+        let operation = tr!("history-operation", count = self.trashed, from = shown(&self.from));
+        match self.failed {
+            0 => operation,
+            n => format!("{operation} {}", tr!("history-failed", count = n)),
+        }
+    }
+}
+
+fn common_parent(targets: &[PathBuf]) -> PathBuf {
+    let mut parents = targets.iter().map(|target| target.parent().unwrap_or(target));
+    let Some(first) = parents.next() else { return PathBuf::new() };
+    parents.fold(first.to_path_buf(), |common, parent| {
+        common.components().zip(parent.components()).take_while(|(a, b)| a == b).map(|(a, _)| a).collect()
+    })
+}
+
+fn shown(dir: &Path) -> String {
+    match dirs_next::home_dir().and_then(|home| dir.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_owned(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => dir.display().to_string(),
+    }
+}
+
+fn load() -> Vec<Operation> {
+    let Ok(content) = state_dir().and_then(|dir| Ok(fs::read_to_string(dir.join(HISTORY_FILE))?)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let id = fields.next()?.to_owned();
+            let time = DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Utc);
+            let trashed = fields.next()?.parse().ok()?;
+            let failed = fields.next()?.parse().ok()?;
+            let from = PathBuf::from(std::ffi::OsString::from_vec(decode_binary(fields.next()?.as_bytes()).into_owned()));
+            Some(Operation { id, time, trashed, failed, from })
+        })
+        .collect()
+}
+
+pub fn print_history() -> Result<()> {
+    let operations = load();
+    if operations.is_empty() {
+        println!("{}", tr!("history-none"));
+        return Ok(());
+    }
+    let items: Vec<_> = iter_trash_items()?.collect();
+    println!("{}", tr!("history-header"));
+    println!("────────────────────────────────────────────────────────────────");
+    for (op, n) in operations.iter().rev().take(HISTORY_SHOWN).zip(1..) {
+        let left = items.iter().filter(|item| item.operation.as_deref() == Some(op.id.as_str())).count();
+        let state = match left {
+            0 => format!("  {}", tr!("history-none-left")),
+            left if left == op.trashed => String::new(),
+            left => format!("  {}", tr!("history-some-left", count = left)),
+        };
+        println!("{n:>2} {}  {}{state}", op.time.with_timezone(&Local).format("%Y-%m-%d %H:%M"), op.describe());
+    }
+    Ok(())
+}
+
+pub fn undo(output: RestoreOutput) -> Result<()> {
+    let items: Vec<_> = iter_trash_items()?.collect();
+    let (op, mut group) = load()
+        .into_iter()
+        .rev()
+        .find_map(|op| {
+            let group: Vec<_> = items.iter().filter(|item| item.operation.as_deref() == Some(op.id.as_str())).cloned().collect();
+            (!group.is_empty()).then_some((op, group))
+        })
+        .ok_or_else(|| anyhow!(tr!("undo-nothing")))?;
+    group.sort_by(trash_order);
+    if matches!(output, RestoreOutput::Human) {
+        println!("{}", tr!("undoing", operation = op.describe(), date = op.time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()));
+    }
+    restore_each(&group, output, true)
+}
@@ -0,0 +1,53 @@
+// src/hooks.rs - User commands run before and after trash, restore, purge and empty
+use crate::config;
+use anyhow::{anyhow, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+pub const HOOKS: &[&str] = &[
+    "pre_trash", "post_trash",
+    "pre_restore", "post_restore",
+    "pre_purge", "post_purge",
+    "pre_empty", "post_empty",
+];
+
+fn run_command(hook: &str, command: &str, paths: &[PathBuf]) -> Result<()> {
+    let list: Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    let list = list.join("\n");
+    debug!("running {hook} hook: {command}");
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("RIP_HOOK", hook)
+        .env("RIP_PATHS", &list)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Cannot run {hook} hook"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{list}");
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("{hook} hook failed ({status})"));
+    }
+    Ok(())
+}
+
+pub fn run(hook: &str, paths: &[PathBuf]) -> Result<()> {
+    let Some(command) = config::get().hooks.get(hook) else {
+        return Ok(());
+    };
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let result = run_command(hook, command, paths);
+    if hook.starts_with("pre_") {
+        return result;
+    }
+    if let Err(e) = result {
+        warn!("{e}");
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+// src/i18n.rs - Localized user-facing messages (Fluent), chosen from LC_ALL, LC_MESSAGES or LANG
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+use unic_langid::LanguageIdentifier;
+
+const BASE_LOCALE: &str = "en";
+const BUNDLED: &[(&str, &str)] = &[("en", include_str!("../locales/en.ftl"))];
+const SYSTEM_LOCALES: &str = "/usr/share/rip/locales";
+
+struct Catalog {
+    translation: Option<FluentBundle<FluentResource>>,
+    base: FluentBundle<FluentResource>,
+}
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+macro_rules! tr {
+    ($id:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message($id, &[$((stringify!($name), fluent_bundle::FluentValue::from($value))),*])
+    };
+}
+pub(crate) use tr;
+
+fn requested_locale() -> Option<LanguageIdentifier> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"].into_iter().find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))?;
+    let name = value.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    match name.as_str() {
+        "" | "C" | "POSIX" => None,
+        name => name.parse().ok(),
+    }
+}
+
+fn locale_dirs() -> Vec<PathBuf> {
+    let user = dirs_next::data_dir().map(|dir| dir.join("rip/locales"));
+    user.into_iter().chain(std::iter::once(PathBuf::from(SYSTEM_LOCALES))).collect()
+}
+
+fn catalog_source(name: &str) -> Option<String> {
+    for dir in locale_dirs() {
+        let file = dir.join(format!("{name}.ftl"));
+        if let Ok(source) = fs::read_to_string(&file) {
+            debug!("messages from {}", file.display());
+            return Some(source);
+        }
+    }
+    BUNDLED.iter().find(|(locale, _)| *locale == name).map(|(_, source)| source.to_string())
+}
+
+fn bundle(locale: LanguageIdentifier, source: String) -> FluentBundle<FluentResource> {
+    let resource = FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+        warn!("{locale}: ignoring {} malformed messages in the translation", errors.len());
+        resource
+    });
+    let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+    bundle.set_use_isolating(false);
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+fn load() -> Catalog {
+    let base_locale: LanguageIdentifier = BASE_LOCALE.parse().expect("valid base locale");
+    let base = bundle(base_locale.clone(), catalog_source(BASE_LOCALE).unwrap_or_default());
+    let translation = requested_locale().filter(|locale| locale.language != base_locale.language).and_then(|locale| {
+        let candidates = [locale.to_string(), locale.language.to_string()];
+        let source = candidates.iter().find_map(|name| catalog_source(name))?;
+        Some(bundle(locale, source))
+    });
+    Catalog { translation, base }
+}
+
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let catalog = CATALOG.get_or_init(load);
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    for bundle in catalog.translation.iter().chain(std::iter::once(&catalog.base)) {
+        let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) else { continue };
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+        if errors.is_empty() {
+            return text.into_owned();
+        }
+        debug!("{id}: {errors:?}");
+    }
+    id.to_owned()
+}
@@ -0,0 +1,262 @@
+// src/journal.rs - Write-ahead journal so an interrupted trash operation is finished or rolled back on the next run
+use crate::fs_utils::{path_size, remove_recursively};
+use crate::sudo;
+use crate::trash::{record_directory_size, trash_roots};
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+use urlencoding::{decode_binary, encode_binary};
+
+const JOURNAL_DIR: &str = ".rip-journal";
+const MOVED: &str = "moved";
+
+fn entry_path(trash: &Path, trashed_name: &str) -> PathBuf {
+    trash.join(JOURNAL_DIR).join(trashed_name)
+}
+
+pub fn begin(trash: &Path, trashed_name: &str, original: &Path, meta: &fs::Metadata, info: &str) -> Result<()> {
+    let dir = trash.join(JOURNAL_DIR);
+    sudo::create_dir_all(&dir)?;
+    let path = entry_path(trash, trashed_name);
+    let mut file = File::create(&path).with_context(|| format!("Cannot write journal entry {}", path.display()))?;
+    let identity = Identity::of(meta);
+    let header = format!(
+        "{}\n{}:{}:{}.{}\n{}\n",
+        std::process::id(),
+        identity.dev,
+        identity.ino,
+        identity.ctime.0,
+        identity.ctime.1,
+        encode_binary(original.as_os_str().as_bytes())
+    );
+    file.write_all((header + info).as_bytes())?;
+    file.sync_all()?;
+    File::open(&dir)?.sync_all()?;
+    Ok(())
+}
+
+pub fn mark_moved(trash: &Path, trashed_name: &str) -> Result<()> {
+    let mut file = OpenOptions::new().append(true).open(entry_path(trash, trashed_name))?;
+    writeln!(file, "{MOVED}")?;
+    file.sync_all()?;
+    Ok(())
+}
+
+pub fn complete(info_file: &Path) {
+    if let (Some(trash), Some(name)) = (
+        info_file.parent().and_then(Path::parent),
+        info_file.file_stem().and_then(|s| s.to_str()),
+    ) {
+        let _ = fs::remove_file(entry_path(trash, name));
+    }
+}
+
+fn split_line(text: &str, valid: impl Fn(&str) -> bool) -> (Option<&str>, &str) {
+    match text.split_once('\n') {
+        Some((line, rest)) if valid(line) => (Some(line), rest),
+        _ => (None, text),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Identity {
+    dev: u64,
+    ino: u64,
+    ctime: (i64, i64),
+}
+
+impl Identity {
+    fn of(meta: &fs::Metadata) -> Self {
+        Identity { dev: meta.dev(), ino: meta.ino(), ctime: (meta.ctime(), meta.ctime_nsec()) }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(':');
+        let (dev, ino, ctime) = (fields.next()?.parse().ok()?, fields.next()?.parse().ok()?, fields.next()?);
+        let (sec, nsec) = ctime.split_once('.')?;
+        Some(Identity { dev, ino, ctime: (sec.parse().ok()?, nsec.parse().ok()?) })
+    }
+}
+
+fn is_renamed_original(dest: &Path, identity: Option<Identity>) -> bool {
+    let Some(identity) = identity else { return false };
+    dest.symlink_metadata().is_ok_and(|meta| (meta.dev(), meta.ino()) == (identity.dev, identity.ino))
+}
+
+fn is_recorded_original(original: &Path, identity: Option<Identity>) -> bool {
+    identity.is_some() && original.symlink_metadata().is_ok_and(|meta| Some(Identity::of(&meta)) == identity)
+}
+
+fn finish(trash: &Path, trashed_name: &str, info_file: &Path, info: &str, dest_meta: &fs::Metadata) -> Result<&'static str> {
+    fs::write(info_file, info)?;
+    sudo::hand_over(info_file);
+    if let (true, Ok(size)) = (dest_meta.is_dir(), path_size(&trash.join("files").join(trashed_name))) {
+        record_directory_size(trash, trashed_name, size, info_file)?;
+    }
+    Ok("finished")
+}
+
+fn replay(trash: &Path, trashed_name: &str) -> Result<(PathBuf, &'static str)> {
+    let entry = entry_path(trash, trashed_name);
+    let content = fs::read_to_string(&entry)?;
+    let (_, content) = split_line(&content, |line| line.parse::<u32>().is_ok());
+    let (identity, content) = split_line(content, |line| Identity::parse(line).is_some());
+    let identity = identity.and_then(Identity::parse);
+    let (original, rest) = content.split_once('\n').context("truncated journal entry")?;
+    let original = PathBuf::from(OsString::from_vec(decode_binary(original.as_bytes()).into_owned()));
+    let moved = rest.lines().any(|line| line == MOVED);
+    let info: String = rest.lines().filter(|line| *line != MOVED).map(|line| format!("{line}\n")).collect();
+    let dest = trash.join("files").join(trashed_name);
+    let info_file = trash.join("info").join(format!("{trashed_name}.trashinfo"));
+
+    let outcome = match dest.symlink_metadata() {
+        _ if info_file.exists() => "completed",
+        Ok(meta) if is_renamed_original(&dest, identity) => finish(trash, trashed_name, &info_file, &info, &meta)?,
+        Ok(meta) if moved && is_recorded_original(&original, identity) => {
+            let is_dir = original.symlink_metadata().is_ok_and(|m| m.is_dir());
+            match remove_recursively(&original) {
+                Err(_) if !is_dir => {
+                    remove_recursively(&dest)?;
+                    "rolled back"
+                }
+                removed => {
+                    if let Err(e) = removed {
+                        warn!("{}: kept the trashed copy, but the original could not be fully removed: {e}", original.display());
+                    }
+                    finish(trash, trashed_name, &info_file, &info, &meta)?
+                }
+            }
+        }
+        Ok(meta) if moved || original.symlink_metadata().is_err() => finish(trash, trashed_name, &info_file, &info, &meta)?,
+        Ok(_) => {
+            remove_recursively(&dest)?;
+            "rolled back"
+        }
+        Err(_) => "rolled back",
+    };
+    fs::remove_file(&entry)?;
+    Ok((original, outcome))
+}
+
+pub fn in_progress(trash: &Path, trashed_name: &str) -> bool {
+    entry_path(trash, trashed_name).exists()
+}
+
+pub fn pending(trash: &Path) -> usize {
+    fs::read_dir(trash.join(JOURNAL_DIR)).map(|entries| entries.count()).unwrap_or(0)
+}
+
+pub fn settle(trash: &Path, trashed_name: &str) {
+    match replay(trash, trashed_name) {
+        Ok((original, outcome)) => debug!("{}: {outcome} the failed trash operation", original.display()),
+        Err(e) => warn!("{}: cannot settle the journal entry: {e}", entry_path(trash, trashed_name).display()),
+    }
+}
+
+fn owner_running(entry: &Path) -> bool {
+    let Some(pid) = fs::read_to_string(entry).ok().and_then(|content| content.lines().next()?.parse::<u32>().ok()) else {
+        return false;
+    };
+    if pid == std::process::id() {
+        return false;
+    }
+    let Ok(pid) = libc::pid_t::try_from(pid) else { return false };
+    let alive = unsafe { libc::kill(pid, 0) } == 0;
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+pub fn recover() {
+    let Ok(roots) = trash_roots() else { return };
+    for trash in roots {
+        let Ok(entries) = fs::read_dir(trash.join(JOURNAL_DIR)) else { continue };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if owner_running(&entry.path()) {
+                debug!("{}: the rip process that wrote it is still running; leaving it alone", entry.path().display());
+                continue;
+            }
+            match replay(&trash, &name) {
+                Ok((original, outcome)) => warn!("{}: {outcome} an interrupted trash operation", original.display()),
+                Err(e) => warn!("{}: cannot recover the interrupted operation: {e}", entry.path().display()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(tag: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rip-test-{}-{tag}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for sub in ["trash/files", "trash/info", "work"] {
+                fs::create_dir_all(dir.join(sub)).unwrap();
+            }
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn interrupted(tmp: &TempDir, copy: bool) -> (PathBuf, PathBuf) {
+        let (trash, original) = (tmp.0.join("trash"), tmp.0.join("work/notes.txt"));
+        fs::write(&original, "trashed").unwrap();
+        begin(&trash, "notes.txt", &original, &original.symlink_metadata().unwrap(), "[Trash Info]\nPath=/x\n").unwrap();
+        let dest = trash.join("files/notes.txt");
+        match copy {
+            true => {
+                fs::copy(&original, &dest).unwrap();
+                mark_moved(&trash, "notes.txt").unwrap();
+            }
+            false => fs::rename(&original, &dest).unwrap(),
+        }
+        (trash, original)
+    }
+
+    #[test]
+    fn keeps_a_file_recreated_after_an_interrupted_rename() {
+        let tmp = TempDir::new("journal-rename");
+        let (trash, original) = interrupted(&tmp, false);
+        fs::write(&original, "new work").unwrap();
+
+        assert_eq!(replay(&trash, "notes.txt").unwrap().1, "finished");
+        assert_eq!(fs::read_to_string(&original).unwrap(), "new work");
+        assert_eq!(fs::read_to_string(trash.join("files/notes.txt")).unwrap(), "trashed");
+        assert!(trash.join("info/notes.txt.trashinfo").exists());
+    }
+
+    #[test]
+    fn keeps_a_file_recreated_after_an_interrupted_copy() {
+        let tmp = TempDir::new("journal-copy");
+        let (trash, original) = interrupted(&tmp, true);
+        fs::remove_file(&original).unwrap();
+        fs::write(&original, "new work").unwrap();
+
+        assert_eq!(replay(&trash, "notes.txt").unwrap().1, "finished");
+        assert_eq!(fs::read_to_string(&original).unwrap(), "new work");
+        assert!(trash.join("info/notes.txt.trashinfo").exists());
+    }
+
+    #[test]
+    fn removes_the_copied_original_it_recorded() {
+        let tmp = TempDir::new("journal-finish");
+        let (trash, original) = interrupted(&tmp, true);
+
+        assert_eq!(replay(&trash, "notes.txt").unwrap().1, "finished");
+        assert!(original.symlink_metadata().is_err());
+        assert_eq!(fs::read_to_string(trash.join("files/notes.txt")).unwrap(), "trashed");
+    }
+}
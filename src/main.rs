@@ -1,9 +1,59 @@
 // src/main.rs - rip: safe rm that moves files to trash instead of deleting them permanently
+mod archive;
+mod audit;
+mod check;
+mod compress;
+mod config;
+mod crypto;
+mod daemon;
+mod dbus;
+mod dedup;
+mod doctor;
+mod duration;
+mod ephemeral;
+mod explain;
+mod filter;
 mod fs_utils;
+mod fuse;
+mod git;
+mod history;
+mod hooks;
+mod i18n;
+mod journal;
+mod metrics;
+mod notify;
+mod policy;
+mod s3;
+mod sandbox;
+mod schedule;
+mod script;
+mod shell;
+mod sudo;
+mod sync;
+mod systemd;
+mod template;
 mod trash;
+mod volume;
+mod watch;
 
 use anyhow::Result;
-use clap::{CommandFactory, Parser};
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use crate::archive::{export_trash, import_trash};
+use crate::config::Overrides;
+use crate::daemon::run_daemon;
+use crate::duration::Span;
+use crate::sync::{replicate, replicate_if_configured, sync_target};
+use crate::filter::ItemFilter;
+use crate::i18n::tr;
+use crate::fs_utils::parse_size;
+use crate::fuse::mount_trash;
+use crate::policy::{autoclean, set_keep_policy, show_keep_policy};
+use crate::s3::retrieve;
+use crate::shell::{print_init, Shell};
+use crate::systemd::install_timer;
+use crate::template::Template;
+use crate::watch::setup_downloads_janitor;
 use crate::trash::*;
 
 #[derive(Parser, Debug)]
@@ -14,54 +64,757 @@ use crate::trash::*;
     author = "Farid",
     long_about = None
 )]
+#[command(group(ArgGroup::new("permanent").args(["purge", "empty"]).multiple(true)))]
+#[command(group(ArgGroup::new("restoring").args(["restore", "restore_last", "restore_path", "undo"]).multiple(true)))]
+#[command(group(ArgGroup::new("preview").args(["autoclean", "clean", "clean_ignored"])))]
 struct Cli {
     #[arg(
         long,
+        hide = true,
         value_name = "POLICY",
-        help = "Show current or set auto-clean policy: ask, never, 30d, ... (no value = show current)"
+        help = "Show current or set auto-clean policy: ask, never, 12h, 30d, 2w, 6m, 1y, ... (no value = show current)"
     )]
     keep: Option<Option<String>>,
 
-    #[arg(long, help = "List items currently in trash")]
+    #[arg(long, hide = true, help = "List items currently in trash")]
     list: bool,
 
-    #[arg(long, help = "Permanently empty the trash")]
+    #[arg(long, hide = true, requires = "list", help = "Show item sizes recorded at deletion time")]
+    long: bool,
+
+    #[arg(long, hide = true, value_name = "NAME", help = "With --list or --purge, only items trashed by user NAME (or uid)")]
+    user: Option<String>,
+
+    #[arg(long, hide = true, requires = "list", help = "Show only the newest trashed version of each original path")]
+    latest_only: bool,
+
+    #[arg(long, hide = true, value_name = "KEY", requires = "list", value_parser = ["dir"], help = "Group the listing by original directory, with per-group counts and sizes")]
+    group_by: Option<String>,
+
+    #[arg(long, hide = true, value_name = "TEMPLATE", requires = "list", value_parser = Template::parse, help = "Print each item through TEMPLATE, e.g. '{index}\\t{date:%F}\\t{size}\\t{path}' (fields: index, date[:strftime], size, bytes, path, name, location, trash, type, tags, note, user; {size:>7} pads)")]
+    format: Option<Template>,
+
+    #[arg(long, hide = true, requires = "list", help = "List items of the default and all named trashes together")]
+    all_trashes: bool,
+
+    #[arg(long, hide = true, help = "Show item counts and recorded sizes per trash")]
+    stats: bool,
+
+    #[arg(long, hide = true, help = "Permanently empty the trash")]
     empty: bool,
 
-    #[arg(long, value_name = "N", help = "Restore the Nth item from trash (1 = newest)")]
-    restore: Option<usize>,
+    #[arg(short = 'i', long, hide = true, requires = "empty", help = "With --empty, pick the items to delete from a list sorted largest first")]
+    interactive: bool,
+
+    #[arg(long, hide = true, value_name = "N", num_args = 0..=1, help = "Permanently delete the Nth item from trash (1 = newest), or with --glob/--older-than/--tag every matching item")]
+    purge: Option<Option<usize>>,
+
+    #[arg(long, hide = true, value_name = "PATTERN", requires = "purge", help = "With --purge, only items whose original path matches PATTERN (e.g. '**/node_modules/**')")]
+    glob: Option<String>,
+
+    #[arg(long, hide = true, value_name = "SPAN", requires = "purge", value_parser = Span::parse, help = "With --purge, only items trashed more than SPAN ago (e.g. 7d)")]
+    older_than: Option<Span>,
+
+    #[arg(long, hide = true, requires = "permanent", help = "Overwrite file contents before deleting (with --purge or --empty)")]
+    shred: bool,
+
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, help = "Permanently delete the oldest items until SIZE is reclaimed (e.g. 5G)")]
+    free: Option<u64>,
+
+    #[arg(long, value_name = "LABEL", value_parser = parse_tag, help = "Tag the trashed files with LABEL (repeatable)")]
+    tag: Vec<String>,
+
+    #[arg(long, value_name = "TEXT", help = "Store a note with the trashed files, shown by rip list --long")]
+    note: Option<String>,
+
+    #[arg(long, num_args = 2, value_names = ["N", "LABEL"], help = "Add LABEL to the Nth item from trash (1 = newest)")]
+    tag_item: Vec<String>,
+
+    #[arg(long, hide = true, value_name = "N|PATH", num_args = 0..=1, help = "Restore the Nth item from trash (1 = newest), or the newest trashed version of PATH")]
+    restore: Option<Option<String>>,
+
+    #[arg(long, hide = true, value_name = "SPAN", requires = "restore", value_parser = Span::parse, help = "With --restore, restore everything trashed within SPAN (e.g. \"10 minutes ago\", 2h), after confirmation")]
+    since: Option<Span>,
+
+    #[arg(long, hide = true, value_name = "DIR", requires = "restore", help = "With --restore, restore everything trashed from under DIR, recreating missing directories, after confirmation")]
+    from: Option<PathBuf>,
+
+    #[arg(long, hide = true, value_name = "PATH", help = "Restore the most recently trashed item whose original path is exactly PATH")]
+    restore_path: Option<PathBuf>,
+
+    #[arg(short = 'u', long, hide = true, value_name = "N", num_args = 0..=1, default_missing_value = "1", help = "Restore the N most recently trashed items (default 1) without listing first")]
+    restore_last: Option<usize>,
+
+    #[arg(long, hide = true, help = "Restore every item still in the trash from the most recent rip invocation")]
+    undo: bool,
+
+    #[arg(long, hide = true, help = "Show recent trash operations, one line per invocation")]
+    history: bool,
+
+    #[arg(long, hide = true, help = "Print the path of the config file")]
+    config_path: bool,
+
+    #[arg(long, hide = true, value_name = "V", requires = "restore", help = "With --restore PATH, restore version V as numbered by --versions")]
+    pick_version: Option<usize>,
+
+    #[arg(long, value_name = "PATH", help = "List the trashed versions of PATH, oldest first")]
+    versions: Option<PathBuf>,
+
+    #[arg(long, value_name = "DIR", help = "Copy DIR into the trash as a restorable item without removing it, as a safety net")]
+    snapshot: Option<PathBuf>,
+
+    #[arg(long, num_args = 0..=2, value_names = ["DAYS", "EXPIRE_DAYS"], help = "Configure the daemon to trash downloads after DAYS (default 30) and expire them EXPIRE_DAYS later (default 30)")]
+    setup_downloads_janitor: Option<Vec<u32>>,
+
+    #[arg(long, hide = true, requires = "restoring", help = "Print only the restored path, for scripts")]
+    porcelain: bool,
+
+    #[arg(short = 'z', hide = true, requires = "porcelain", help = "With --porcelain, terminate paths with NUL instead of newline")]
+    null: bool,
+
+    #[arg(long, value_name = "ARCHIVE", help = "Export all trash items with their metadata to a .tar or .tar.zst archive")]
+    export: Option<PathBuf>,
+
+    #[arg(long, value_name = "ARCHIVE", help = "Import trash items from an archive created by --export")]
+    import: Option<PathBuf>,
+
+    #[arg(long, value_name = "NAME", help = "Pull an item archived to S3 back into the trash (without NAME: list archived items)")]
+    retrieve: Option<Option<String>>,
+
+    #[arg(long, global = true, value_name = "NAME", help = "Use the settings of a named profile from the config")]
+    profile: Option<String>,
+
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "trash_dir", help = "Use a named trash from the config")]
+    trash: Option<String>,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Use PATH as the trash directory for this command (created if missing)")]
+    trash_dir: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", help = "Open the trash in the file manager (with PATH: the trash of the volume holding PATH)")]
+    open: Option<Option<PathBuf>>,
+
+    #[arg(long, global = true, help = "Under sudo, use root's trash instead of the invoking user's")]
+    root_trash: bool,
+
+    #[arg(long, value_name = "DIR", help = "Browse the trash as a read-only FUSE filesystem mounted at DIR")]
+    mount: Option<PathBuf>,
+
+    #[arg(long, help = "Check the trash directories for inconsistencies and report them")]
+    check: bool,
+
+    #[arg(long, requires = "check", help = "With --check, repair what can be repaired instead of only reporting it")]
+    repair: bool,
+
+    #[arg(long, help = "Open a shell with a throwaway trash and sample files to try rip without touching real data")]
+    sandbox: bool,
+
+    #[arg(long, value_name = "SPAN", help = "Inside rip --sandbox, move the clock forward by SPAN (e.g. 10d) to watch the policy expire items")]
+    sandbox_clock: Option<String>,
+
+    #[arg(long, global = true, help = "Describe what the rest of the command line would do (targets, trash, rename or copy, prompts, space) without doing it")]
+    explain: bool,
+
+    #[arg(long, help = "Check trash permissions, volume trashes, config, other trash tools and the locale, and suggest fixes")]
+    doctor: bool,
+
+    #[arg(long, help = "Remove dangling records, stale directorysizes entries and leftover temporary files from the trash")]
+    gc: bool,
+
+    #[arg(long, value_name = "DIR", help = "Create records for stray files in the trash so they can be listed and restored (with DIR: record DIR/NAME as their original path; nothing is moved until you restore)")]
+    adopt: Option<Option<PathBuf>>,
+
+    #[arg(long, help = "Mirror the trash to the configured remote sync target")]
+    sync: bool,
+
+    #[arg(long, help = "Run in the background, applying the auto-clean policy periodically")]
+    daemon: bool,
+
+    #[arg(long, help = "Apply the auto-clean policy once and exit")]
+    autoclean: bool,
+
+    #[arg(short = 'n', long, requires = "preview", help = "Show what --autoclean, --clean or --clean-ignored would do without doing it")]
+    dry_run: bool,
+
+    #[arg(long, help = "Install a user systemd timer running --autoclean daily")]
+    install_timer: bool,
+
+    #[arg(long, value_name = "DIR", help = "Trash the files ignored by git in DIR (default: current directory), like a recoverable git clean -X")]
+    clean_ignored: Option<Option<PathBuf>>,
+
+    #[arg(long, value_name = "DIR", help = "Trash the files git does not track in DIR (default: current directory), like a recoverable git clean")]
+    clean: Option<Option<PathBuf>>,
+
+    #[arg(short = 'd', long, requires = "clean", help = "With --clean, also trash untracked directories")]
+    directories: bool,
+
+    #[arg(long, help = "For find -exec/xargs: no prompts or auto-clean, buffered metadata, one status line per path")]
+    batch: bool,
+
+    #[arg(long, hide = true, value_name = "SHELL", help = "Print shell integration (rm wrapper, unrm, trash count in the prompt) for eval")]
+    init: Option<Shell>,
+
+    #[arg(long, help = "Do not warn about git repositories with uncommitted or unpushed work")]
+    no_git_check: bool,
+
+    #[arg(short = 'f', long, help = "Trash the current directory or one of its parents without asking")]
+    force: bool,
+
+    #[arg(long, global = true, value_name = "LEVEL", default_value = "warn", help = "Diagnostic verbosity: error, warn, info, debug or trace")]
+    log_level: tracing::Level,
 
     #[arg(value_name = "FILE", trailing_var_arg = true, help = "Files, directories or symlinks to move to trash")]
     files: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Move files, directories or symlinks to the trash (the default: rip FILE... does the same)")]
+    Trash(TrashArgs),
+    #[command(about = "List items in the trash, newest first")]
+    List(ListArgs),
+    #[command(about = "Restore items from the trash")]
+    Restore(RestoreArgs),
+    #[command(about = "Permanently delete everything in the trash, or only tagged items")]
+    Empty(EmptyArgs),
+    #[command(about = "Permanently delete the Nth item, or every item matching the filters after confirmation")]
+    Purge(PurgeArgs),
+    #[command(about = "Restore everything the most recent rip invocation trashed")]
+    Undo(OutputArgs),
+    #[command(about = "Show recent trash operations, one line per invocation")]
+    History,
+    #[command(about = "Show item counts, sizes and free space per trash")]
+    Stats,
+    #[command(about = "Show or change the configuration")]
+    Config(ConfigArgs),
+    #[command(about = "Print shell integration (rm wrapper, unrm, trash count in the prompt) for eval")]
+    Init {
+        #[arg(value_enum, help = "Shell to print the integration for")]
+        shell: Shell,
+    },
+}
+
+#[derive(Args, Debug)]
+struct TrashArgs {
+    #[arg(value_name = "FILE", required = true, trailing_var_arg = true, help = "Files, directories or symlinks to move to trash")]
+    files: Vec<String>,
+
+    #[arg(long, value_name = "LABEL", value_parser = parse_tag, help = "Tag the trashed files with LABEL (repeatable)")]
+    tag: Vec<String>,
+
+    #[arg(long, value_name = "TEXT", help = "Store a note with the trashed files, shown by rip list --long")]
+    note: Option<String>,
+
+    #[arg(short = 'f', long, help = "Trash the current directory or one of its parents without asking")]
+    force: bool,
+
+    #[arg(long, help = "Do not warn about git repositories with uncommitted or unpushed work")]
+    no_git_check: bool,
+
+    #[arg(long, help = "For find -exec/xargs: no prompts or auto-clean, buffered metadata, one status line per path")]
+    batch: bool,
+}
+
+#[derive(Args, Debug)]
+struct ListArgs {
+    #[arg(long, help = "Show item sizes recorded at deletion time")]
+    long: bool,
+
+    #[arg(long, value_name = "NAME", help = "Only items trashed by user NAME (or uid)")]
+    user: Option<String>,
+
+    #[arg(long, value_name = "LABEL", value_parser = parse_tag, help = "Only items carrying LABEL (repeatable)")]
+    tag: Vec<String>,
+
+    #[arg(long, help = "Show only the newest trashed version of each original path")]
+    latest_only: bool,
+
+    #[arg(long, value_name = "KEY", value_parser = ["dir"], help = "Group the listing by original directory, with per-group counts and sizes")]
+    group_by: Option<String>,
+
+    #[arg(long, value_name = "TEMPLATE", value_parser = Template::parse, help = "Print each item through TEMPLATE, e.g. '{index}\\t{date:%F}\\t{size}\\t{path}'")]
+    format: Option<Template>,
+
+    #[arg(long, help = "List items of the default and all named trashes together")]
+    all_trashes: bool,
+}
+
+#[derive(Args, Debug)]
+struct OutputArgs {
+    #[arg(long, help = "Print only the restored path, for scripts")]
+    porcelain: bool,
+
+    #[arg(short = 'z', requires = "porcelain", help = "With --porcelain, terminate paths with NUL instead of newline")]
+    null: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("which").args(["target", "last", "path", "since", "from"]).required(true)))]
+struct RestoreArgs {
+    #[arg(value_name = "N|PATH", help = "The Nth item (1 = newest), or the newest trashed version of PATH")]
+    target: Option<String>,
+
+    #[arg(long, value_name = "V", requires = "target", help = "Restore version V of PATH as numbered by --versions")]
+    pick_version: Option<usize>,
+
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1", conflicts_with_all = ["target", "path"], help = "The N most recently trashed items (default 1)")]
+    last: Option<usize>,
+
+    #[arg(long, value_name = "PATH", conflicts_with = "target", help = "The most recently trashed item whose original path is exactly PATH")]
+    path: Option<PathBuf>,
+
+    #[arg(long, value_name = "SPAN", conflicts_with_all = ["target", "last", "path"], value_parser = Span::parse, help = "Everything trashed within SPAN (e.g. \"10 minutes ago\", 2h), after confirmation")]
+    since: Option<Span>,
+
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["target", "last", "path"], help = "Everything trashed from under DIR, recreating missing directories, after confirmation")]
+    from: Option<PathBuf>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+struct EmptyArgs {
+    #[arg(short = 'i', long, help = "Pick the items to delete from a list sorted largest first")]
+    interactive: bool,
+
+    #[arg(long, value_name = "LABEL", value_parser = parse_tag, help = "Only items carrying LABEL (repeatable)")]
+    tag: Vec<String>,
+
+    #[arg(long, help = "Overwrite file contents before deleting")]
+    shred: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("which").args(["n", "glob", "older_than", "tag", "user"]).required(true).multiple(true)))]
+struct PurgeArgs {
+    #[arg(value_name = "N", help = "The Nth item (1 = newest)")]
+    n: Option<usize>,
+
+    #[arg(long, value_name = "PATTERN", help = "Only items whose original path matches PATTERN (e.g. '**/node_modules/**')")]
+    glob: Option<String>,
+
+    #[arg(long, value_name = "SPAN", value_parser = Span::parse, help = "Only items trashed more than SPAN ago (e.g. 7d)")]
+    older_than: Option<Span>,
+
+    #[arg(long, value_name = "LABEL", value_parser = parse_tag, help = "Only items carrying LABEL (repeatable)")]
+    tag: Vec<String>,
+
+    #[arg(long, value_name = "NAME", help = "Only items trashed by user NAME (or uid)")]
+    user: Option<String>,
+
+    #[arg(long, help = "Overwrite file contents before deleting")]
+    shred: bool,
+}
+
+#[derive(Args, Debug)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    setting: Option<ConfigCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    #[command(about = "Show or set the auto-clean policy: ask, never, 12h, 30d, 2w, 6m, 1y, ...")]
+    Keep { policy: Option<String> },
+    #[command(about = "Print the path of the config file")]
+    Path,
+}
+
+impl Command {
+    fn apply(self, cli: &mut Cli) {
+        match self {
+            Command::Trash(args) => {
+                cli.files = args.files;
+                cli.tag.extend(args.tag);
+                cli.note = args.note.or(cli.note.take());
+                cli.force |= args.force;
+                cli.no_git_check |= args.no_git_check;
+                cli.batch |= args.batch;
+            }
+            Command::List(args) => {
+                cli.list = true;
+                cli.long |= args.long;
+                cli.user = args.user.or(cli.user.take());
+                cli.tag.extend(args.tag);
+                cli.latest_only |= args.latest_only;
+                cli.group_by = args.group_by.or(cli.group_by.take());
+                cli.format = args.format.or(cli.format.take());
+                cli.all_trashes |= args.all_trashes;
+            }
+            Command::Restore(args) => {
+                match (args.last, args.path) {
+                    (Some(n), _) => cli.restore_last = Some(n),
+                    (None, Some(path)) => cli.restore_path = Some(path),
+                    (None, None) => cli.restore = Some(args.target),
+                }
+                cli.pick_version = args.pick_version;
+                cli.since = args.since;
+                cli.from = args.from;
+                cli.porcelain |= args.output.porcelain;
+                cli.null |= args.output.null;
+            }
+            Command::Empty(args) => {
+                cli.empty = true;
+                cli.interactive |= args.interactive;
+                cli.tag.extend(args.tag);
+                cli.shred |= args.shred;
+            }
+            Command::Purge(args) => {
+                cli.purge = Some(args.n);
+                cli.glob = args.glob;
+                cli.older_than = args.older_than;
+                cli.tag.extend(args.tag);
+                cli.user = args.user.or(cli.user.take());
+                cli.shred |= args.shred;
+            }
+            Command::Undo(args) => {
+                cli.undo = true;
+                cli.porcelain |= args.porcelain;
+                cli.null |= args.null;
+            }
+            Command::History => cli.history = true,
+            Command::Stats => cli.stats = true,
+            Command::Config(args) => match args.setting {
+                Some(ConfigCommand::Keep { policy }) => cli.keep = Some(policy),
+                Some(ConfigCommand::Path) => cli.config_path = true,
+                None => cli.keep = Some(None),
+            },
+            Command::Init { shell } => cli.init = Some(shell),
+        }
+    }
+}
+
+const RM_FLAGS: &str = "rRfidIv";
+
+fn dash_hint(arg: &str) -> Option<String> {
+    if Path::new(arg).symlink_metadata().is_ok() {
+        return Some(format!("rip: {arg} looks like an option, but a file by that name exists; to trash it, use: rip -- {arg}  (or rip ./{arg})"));
+    }
+    let flags = arg.strip_prefix('-').filter(|flags| !flags.starts_with('-'))?;
+    flags.chars().all(|c| RM_FLAGS.contains(c)).then(|| {
+        format!(
+            "rip: {arg} is an rm option; rip needs none: directories are trashed whole and nothing is deleted for good.\n  \
+             Just run rip PATH..., or eval \"$(rip init bash)\" for an rm wrapper that accepts rm's options.\n  \
+             To trash a file named {arg}, use: rip -- {arg}"
+        )
+    })
+}
+
+fn command_hint(name: &str) -> Option<String> {
+    Path::new(name).symlink_metadata().ok()?;
+    Some(format!("rip: {name} is a rip command, but a file by that name exists here; to trash the file, use: rip -- {name}  (or rip ./{name})"))
+}
+
+fn check_dash_args(args: &[std::ffi::OsString]) -> Option<String> {
+    let mut command = Cli::command();
+    command.build();
+    let values = |arg: &clap::Arg| arg.get_num_args().map_or(0, |n| n.max_values());
+    let mut args = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+    let mut after_trash = false;
+    while let Some(arg) = args.next() {
+        if arg == "--" || arg == "-" {
+            return None;
+        }
+        if !arg.starts_with('-') {
+            if after_trash || command.find_subcommand(arg.as_ref()).is_none() {
+                return None;
+            }
+            if let Some(hint) = command_hint(&arg) {
+                return Some(hint);
+            }
+            if arg != "trash" {
+                return None;
+            }
+            after_trash = true;
+            continue;
+        }
+        let skip = if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = long.split_once('=').map_or((long, false), |(name, _)| (name, true));
+            let known = command.get_arguments().find(|a| a.get_long() == Some(name) || a.get_all_aliases().is_some_and(|aliases| aliases.contains(&name)));
+            match known {
+                Some(_) if inline => 0,
+                Some(a) => values(a),
+                None if name == "help" => 0,
+                None => return dash_hint(&arg),
+            }
+        } else {
+            let mut skip = 0;
+            for (i, c) in arg.char_indices().skip(1) {
+                match command.get_arguments().find(|a| a.get_short() == Some(c)) {
+                    Some(a) if values(a) > 0 => {
+                        skip = if i + 1 == arg.len() { values(a) } else { 0 };
+                        break;
+                    }
+                    Some(_) => {}
+                    None if c == 'h' => {}
+                    None => return dash_hint(&arg),
+                }
+            }
+            skip
+        };
+        for _ in 0..skip {
+            let mut peek = args.clone();
+            match peek.next() {
+                Some(value) if !value.starts_with('-') => {
+                    args.next();
+                }
+                _ => break,
+            }
+        }
+    }
+    None
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if let Some(hint) = check_dash_args(&args) {
+        eprintln!("{hint}");
+        std::process::exit(2);
+    }
+    let mut cli = Cli::parse_from(args);
+    if let Some(command) = cli.command.take() {
+        command.apply(&mut cli);
+    }
+    tracing_subscriber::fmt()
+        .with_max_level(cli.log_level)
+        .with_writer(std::io::stderr)
+        .without_time()
+        .with_target(false)
+        .init();
+    let initialized = config::init(Overrides {
+        profile: cli.profile.clone(),
+        trash: cli.trash.clone(),
+        trash_dir: cli.trash_dir.clone(),
+        root_trash: cli.root_trash,
+    });
+    if cli.doctor {
+        if !doctor::run_doctor(initialized.err()) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    initialized?;
+    if cli.explain {
+        return explain(&cli);
+    }
+    journal::recover();
 
     if let Some(policy_opt) = cli.keep {
         match policy_opt {
             Some(policy) => { set_keep_policy(&policy)?; }
             None => { show_keep_policy()?; }
         }
+    } else if cli.list && cli.all_trashes {
+        list_all_trashes(cli.long)?;
+    } else if cli.list && cli.group_by.is_some() {
+        list_grouped(&cli.tag)?;
     } else if cli.list {
-        list_trash()?;
+        list_trash(cli.long, &item_filter(&cli)?, cli.latest_only, cli.format.as_ref())?;
+    } else if cli.config_path {
+        println!("{}", config::config_path()?.display());
+    } else if cli.stats {
+        print_stats()?;
+    } else if cli.history {
+        history::print_history()?;
+    } else if cli.empty && cli.interactive {
+        empty_interactive(&cli.tag, cli.shred)?;
+    } else if cli.empty && !cli.tag.is_empty() {
+        empty_tagged(&cli.tag, cli.shred)?;
     } else if cli.empty {
-        empty_trash()?;
-    } else if let Some(n) = cli.restore {
-        restore_nth(n)?;
+        empty_trash(cli.shred)?;
+    } else if let Some(n) = cli.purge {
+        let filter = item_filter(&cli)?;
+        match (n, filter.is_empty()) {
+            (Some(n), true) => purge_nth(n, cli.shred)?,
+            (None, false) => purge_matching(&filter, cli.shred)?,
+            (Some(_), false) => return Err(anyhow::anyhow!("--glob, --older-than, --tag and --user take the place of N")),
+            (None, true) => return Err(anyhow::anyhow!("--purge needs N, --glob PATTERN, --older-than SPAN, --tag LABEL or --user NAME")),
+        }
+    } else if let Some(bytes) = cli.free {
+        free_space(bytes)?;
+    } else if let [n, label] = cli.tag_item.as_slice() {
+        tag_nth(n.parse().map_err(|_| anyhow::anyhow!("Invalid item number: {n}"))?, label)?;
+    } else if let Some(path) = &cli.restore_path {
+        restore_path(path, restore_output(&cli))?;
+    } else if cli.undo {
+        history::undo(restore_output(&cli))?;
+    } else if let Some(n) = cli.restore_last {
+        restore_last(n, restore_output(&cli))?;
+    } else if let Some(target) = &cli.restore {
+        let output = restore_output(&cli);
+        let filtered = cli.since.is_some() || cli.from.is_some();
+        match (target, filtered) {
+            (None, true) => restore_matching(cli.since, cli.from.as_deref(), output)?,
+            (Some(_), true) => return Err(anyhow::anyhow!("--since and --from take the place of N or PATH")),
+            (None, false) => return Err(anyhow::anyhow!("--restore needs N, PATH, --since SPAN or --from DIR")),
+            (Some(target), false) => match target.parse::<usize>() {
+                Ok(n) if cli.pick_version.is_none() => restore_nth(n, output)?,
+                _ => restore_version(Path::new(target), cli.pick_version, output)?,
+            },
+        }
+    } else if cli.sandbox {
+        sandbox::run_sandbox()?;
+    } else if let Some(span) = &cli.sandbox_clock {
+        sandbox::advance_clock(span)?;
+    } else if let Some(days) = &cli.setup_downloads_janitor {
+        setup_downloads_janitor(days.first().copied().unwrap_or(30), days.get(1).copied().unwrap_or(30))?;
+    } else if let Some(path) = &cli.snapshot {
+        snapshot(path, &cli.tag)?;
+    } else if let Some(path) = &cli.versions {
+        list_versions(path)?;
+    } else if cli.autoclean {
+        autoclean(cli.dry_run)?;
+    } else if cli.install_timer {
+        install_timer()?;
+    } else if let Some(path) = &cli.export {
+        export_trash(path)?;
+    } else if let Some(path) = &cli.import {
+        import_trash(path)?;
+    } else if let Some(name) = &cli.retrieve {
+        retrieve(name.as_deref())?;
+    } else if let Some(path) = &cli.open {
+        open_trash(path.as_deref())?;
+    } else if let Some(dir) = &cli.mount {
+        mount_trash(dir)?;
+    } else if cli.check {
+        if !check::run_check(cli.repair)? {
+            std::process::exit(1);
+        }
+    } else if cli.gc {
+        check::run_gc()?;
+    } else if let Some(dir) = &cli.adopt {
+        check::run_adopt(dir.as_deref())?;
+    } else if cli.sync {
+        replicate()?;
+        println!("Trash mirrored to {}", sync_target().unwrap_or_default());
+    } else if cli.daemon {
+        run_daemon()?;
+    } else if let Some(shell) = cli.init {
+        print_init(shell)?;
+    } else if let Some(dir) = &cli.clean_ignored {
+        clean(dir.as_deref(), &cli.tag, true, true, cli.dry_run)?;
+    } else if let Some(dir) = &cli.clean {
+        clean(dir.as_deref(), &cli.tag, false, cli.directories, cli.dry_run)?;
     } else if cli.files.is_empty() {
         Cli::command().print_help()?;
+    } else if !trash_files(&cli.files, &cli.tag, cli.note.as_deref(), !cli.no_git_check && !cli.batch, !cli.force, cli.batch)? {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn item_filter(cli: &Cli) -> Result<ItemFilter> {
+    ItemFilter::new(cli.glob.as_deref(), cli.older_than, &cli.tag, cli.user.as_deref())
+}
+
+fn restore_output(cli: &Cli) -> RestoreOutput {
+    match cli.porcelain {
+        true => RestoreOutput::Porcelain { nul: cli.null },
+        false => RestoreOutput::Human,
+    }
+}
+
+fn explain(cli: &Cli) -> Result<()> {
+    if cli.empty {
+        explain::explain_empty(&cli.tag, cli.shred)
+    } else if let Some(Some(n)) = cli.purge {
+        explain::explain_purge(n, cli.shred)
+    } else if cli.purge.is_some() {
+        explain::explain_purge_matching(&item_filter(cli)?, cli.shred)
+    } else if let Some(dir) = &cli.clean_ignored {
+        explain::explain_trash(&git::untracked_paths(dir.as_deref().unwrap_or(Path::new(".")), true, true)?, false, true)
+    } else if let Some(dir) = &cli.clean {
+        explain::explain_trash(&git::untracked_paths(dir.as_deref().unwrap_or(Path::new(".")), false, cli.directories)?, false, true)
+    } else if !cli.files.is_empty() {
+        explain::explain_trash(&cli.files, !cli.no_git_check && !cli.batch, !cli.force)
     } else {
-        let mut had_error = false;
-        for path in &cli.files {
-            if let Err(e) = move_to_trash(path) {
+        Err(anyhow::anyhow!("--explain describes trashing files, --clean, --clean-ignored, --empty and --purge"))
+    }
+}
+
+fn clean(dir: Option<&Path>, tags: &[String], ignored: bool, directories: bool, dry_run: bool) -> Result<()> {
+    let paths = git::untracked_paths(dir.unwrap_or(Path::new(".")), ignored, directories)?;
+    if paths.is_empty() {
+        println!("Nothing to clean");
+    } else if dry_run {
+        for path in &paths {
+            println!("Would trash {path}");
+        }
+    } else if !trash_files(&paths, tags, None, false, true, false)? {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn trash_files(files: &[String], tags: &[String], note: Option<&str>, git_check: bool, cwd_check: bool, bulk: bool) -> Result<bool> {
+    let mut files: Vec<(&String, PathBuf)> = files.iter()
+        .map(|f| (f, std::path::absolute(f).unwrap_or_else(|_| PathBuf::from(f))))
+        .collect();
+    files.sort_by_key(|(_, target)| contains_current_dir(target));
+    let (files, targets): (Vec<&String>, Vec<PathBuf>) = files.into_iter().unzip();
+    hooks::run("pre_trash", &targets)?;
+    if !bulk {
+        clean_if_due();
+    }
+    let mut had_error = false;
+    let mut trashed = Vec::new();
+    let mut batch = match bulk {
+        true => InfoBatch::with_limit(BULK_INFO_BATCH_SIZE),
+        false => InfoBatch::new(),
+    };
+    if !tags.is_empty() {
+        batch.annotate("X-Rip-Tags", &tags.join(","));
+    }
+    if let Some(note) = note {
+        batch.annotate("X-Rip-Note", &urlencoding::encode(note));
+    }
+    let mut operation = history::Operation::start();
+    batch.annotate("X-Rip-Operation", &operation.id);
+    let attempted = targets.clone();
+    for (path, target) in files.iter().zip(targets) {
+        if git_check && !git::confirm_repositories(&target) {
+            println!("{}", tr!("skipped", path = path.as_str()));
+            continue;
+        }
+        if cwd_check && contains_current_dir(&target) {
+            println!("{}", tr!("holds-current-dir", path = path.as_str()));
+            if bulk || !confirm(&tr!("trash-anyway")) {
+                println!("{}", tr!("skipped", path = path.as_str()));
+                continue;
+            }
+        }
+        match move_to_trash(path, &mut batch) {
+            Ok(()) => {
+                if bulk {
+                    println!("trashed {path}");
+                }
+                trashed.push(target);
+            }
+            Err(e) if bulk => {
+                println!("failed {path}: {e}");
+                operation.failed += 1;
+                had_error = true;
+            }
+            Err(e) => {
                 eprintln!("rip: {path}: {e}");
+                operation.failed += 1;
                 had_error = true;
             }
         }
-        if had_error {
-            std::process::exit(1);
-        }
     }
-    Ok(())
-}
\ No newline at end of file
+    if let Err(e) = batch.flush() {
+        eprintln!("rip: {e}");
+        had_error = true;
+    }
+    operation.trashed = trashed.len();
+    operation.finish(&attempted);
+    replicate_if_configured();
+    if !trashed.is_empty() {
+        policy::warn_near_limit();
+    }
+    hooks::run("post_trash", &trashed)?;
+    Ok(!had_error)
+}
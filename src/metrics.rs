@@ -0,0 +1,112 @@
+// src/metrics.rs - Prometheus text-format metrics served by the daemon
+use crate::config;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_LINE: u64 = 8192;
+
+#[derive(Default)]
+struct Latency {
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Metrics {
+    items: usize,
+    bytes: u64,
+    expired_items: u64,
+    reclaimed_bytes: u64,
+    refresh: Latency,
+    cleanup: Latency,
+}
+
+static METRICS: Mutex<Option<Metrics>> = Mutex::new(None);
+
+fn update(f: impl FnOnce(&mut Metrics)) {
+    if let Ok(mut metrics) = METRICS.lock() {
+        f(metrics.get_or_insert_with(Metrics::default));
+    }
+}
+
+pub fn record_index(items: usize, bytes: u64, took: Duration) {
+    update(|m| {
+        m.items = items;
+        m.bytes = bytes;
+        m.refresh.sum += took.as_secs_f64();
+        m.refresh.count += 1;
+    });
+}
+
+pub fn record_cleanup(expired: usize, bytes: u64, took: Duration) {
+    update(|m| {
+        m.expired_items += expired as u64;
+        m.reclaimed_bytes += bytes;
+        m.cleanup.sum += took.as_secs_f64();
+        m.cleanup.count += 1;
+    });
+}
+
+fn render() -> String {
+    let guard = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    let default = Metrics::default();
+    let m = guard.as_ref().unwrap_or(&default);
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}");
+    };
+    metric("rip_trash_items", "gauge", "Items currently in the trash.", m.items.to_string());
+    metric("rip_trash_bytes", "gauge", "Bytes currently held by the trash.", m.bytes.to_string());
+    metric("rip_expired_items_total", "counter", "Items removed by auto-clean.", m.expired_items.to_string());
+    metric("rip_reclaimed_bytes_total", "counter", "Bytes reclaimed by auto-clean.", m.reclaimed_bytes.to_string());
+    for (name, help, latency) in [
+        ("rip_index_refresh_duration_seconds", "Time spent re-reading the trash index.", &m.refresh),
+        ("rip_cleanup_duration_seconds", "Time spent applying the auto-clean policy.", &m.cleanup),
+    ] {
+        let _ = writeln!(
+            out,
+            "# HELP {name} {help}\n# TYPE {name} summary\n{name}_sum {}\n{name}_count {}",
+            latency.sum, latency.count
+        );
+    }
+    out
+}
+
+fn respond(stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", render())
+    } else {
+        ("404 Not Found", "Not found\n".to_owned())
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+pub fn serve() -> Result<()> {
+    let Some(addr) = config::get().metrics.as_deref() else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind(addr).with_context(|| format!("Cannot serve metrics on {addr}"))?;
+    println!("Serving metrics on http://{addr}/metrics");
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = respond(stream);
+        }
+    });
+    Ok(())
+}
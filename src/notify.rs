@@ -0,0 +1,44 @@
+// src/notify.rs - Desktop notifications for auto-clean deletions and large trash operations
+use crate::config;
+use crate::fs_utils::human_size;
+use crate::i18n::tr;
+use std::path::Path;
+
+const LARGE_OPERATION: usize = 100;
+
+pub fn notify_auto_clean(count: usize, bytes: u64) {
+    if count == 0 || config::get().notifications == Some(false) {
+        return;
+    }
+    let body = format!("Permanently deleted {count} items ({}) from the trash", human_size(bytes));
+    send("rip auto-clean", &body);
+}
+
+pub fn notify_trashed(count: usize, from: &Path) {
+    if count < LARGE_OPERATION || config::get().notifications == Some(false) {
+        return;
+    }
+    let body = tr!("notify-trashed", count = count, from = from.display().to_string());
+    send("rip", &body);
+}
+
+#[cfg(feature = "notifications")]
+fn send(summary: &str, body: &str) {
+    let _ = notify_rust::Notification::new()
+        .appname("rip")
+        .summary(summary)
+        .body(body)
+        .icon("user-trash-full")
+        .show();
+}
+
+#[cfg(not(feature = "notifications"))]
+fn send(_summary: &str, _body: &str) {
+    use std::sync::Once;
+    use tracing::warn;
+
+    static WARNED: Once = Once::new();
+    if config::get().notifications == Some(true) {
+        WARNED.call_once(|| warn!("rip was built without notification support (rebuild with --features notifications); ignoring notifications = true"));
+    }
+}
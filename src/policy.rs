@@ -0,0 +1,327 @@
+// src/policy.rs - Auto-clean retention policies, global and per original directory
+use crate::archive::{archive_dir, archive_items};
+use crate::compress::{compress_aged, compression_candidates};
+use crate::config;
+use crate::duration::Span;
+use crate::fs_utils::{disk_usage, human_size};
+use crate::notify::notify_auto_clean;
+use crate::s3::{s3_config, upload_items};
+use crate::sandbox;
+use crate::schedule;
+use crate::script;
+use crate::trash::{confirm, load_trash_items, purge_item, state_dir, trash_order, trash_roots, TrashItem};
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use glob::{MatchOptions, Pattern};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+const WARNING_LEVELS: [u8; 3] = [100, 90, 80];
+const LEVEL_FILE: &str = "pressure-level";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepPolicy {
+    Never,
+    Keep(Span),
+    AskBeforeDelete,
+    UntilFull(u8),
+}
+
+impl fmt::Display for KeepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeepPolicy::Never => write!(f, "never delete automatically"),
+            KeepPolicy::Keep(span) if span.is_zero() => write!(f, "delete immediately"),
+            KeepPolicy::Keep(span) => write!(f, "delete items older than {span}"),
+            KeepPolicy::AskBeforeDelete => write!(f, "ask before deleting items older than 30 days"),
+            KeepPolicy::UntilFull(percent) => write!(f, "delete oldest items while the trash volume is over {percent}% full"),
+        }
+    }
+}
+
+pub fn parse_keep_policy(policy: &str) -> Result<KeepPolicy> {
+    let p = policy.trim().to_lowercase();
+    Ok(match p.as_str() {
+        "never" => KeepPolicy::Never,
+        "ask" => KeepPolicy::AskBeforeDelete,
+        s if s.starts_with("until-") => {
+            let percent = s.strip_prefix("until-")
+                .and_then(|r| r.strip_suffix("%-full"))
+                .and_then(|r| r.parse::<u8>().ok())
+                .filter(|p| (1..=100).contains(p))
+                .ok_or_else(|| anyhow!("Invalid disk-pressure policy: {s} (expected e.g. until-90%-full)"))?;
+            KeepPolicy::UntilFull(percent)
+        }
+        s => {
+            let span = Span::parse(s)
+                .map_err(|e| anyhow!("{e}\nValid policies: ask | never | 12h | 30d | 2w | 6m | 1y | \"2 weeks\" | until-90%-full"))?;
+            if span.is_zero() {
+                KeepPolicy::Never
+            } else {
+                KeepPolicy::Keep(span)
+            }
+        }
+    })
+}
+
+pub fn keep_policy() -> KeepPolicy {
+    config::get()
+        .keep
+        .as_deref()
+        .and_then(|k| parse_keep_policy(k).ok())
+        .unwrap_or(KeepPolicy::Keep(Span::Days(30)))
+}
+
+pub fn directory_rule(path: &Path) -> Option<(&'static str, KeepPolicy)> {
+    config::get()
+        .policy
+        .iter()
+        .filter_map(|(dir, rule)| {
+            let keep = parse_keep_policy(rule.keep.as_deref()?).ok()?;
+            let root = config::expand_path(dir);
+            config::normalized(path).starts_with(config::normalized(&root)).then(|| (root.components().count(), dir.as_str(), keep))
+        })
+        .max_by_key(|(depth, _, _)| *depth)
+        .map(|(_, dir, keep)| (dir, keep))
+}
+
+pub fn never_expires(path: &Path) -> bool {
+    let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+    config::get().never_expire.iter().any(|pattern| {
+        Pattern::new(&config::normalized(&config::expand_path(pattern)).to_string_lossy())
+            .is_ok_and(|p| p.matches_path_with(&config::normalized(path), options))
+    })
+}
+
+pub fn policy_for(path: &Path) -> KeepPolicy {
+    directory_rule(path).map(|(_, keep)| keep).unwrap_or_else(keep_policy)
+}
+
+pub fn set_keep_policy(policy: &str) -> Result<()> {
+    let keep = parse_keep_policy(policy)?;
+    config::set_value("keep", &policy.trim().to_lowercase())?;
+    println!("Auto-clean policy set to: {keep}");
+    Ok(())
+}
+
+pub fn show_keep_policy() -> Result<()> {
+    println!("Current auto-clean policy: {}", keep_policy());
+    for (dir, rule) in &config::get().policy {
+        if let Some(keep) = rule.keep.as_deref().and_then(|k| parse_keep_policy(k).ok()) {
+            println!("  {dir}: {keep}");
+        }
+    }
+    if let Some(script) = script::script_path() {
+        println!("Policy script: {}", script.display());
+    }
+    if !config::get().never_expire.is_empty() {
+        println!("Never expire: {}", config::get().never_expire.join(", "));
+    }
+    if let Some(schedule) = &config::get().schedule {
+        println!("Auto-clean schedule: {schedule}");
+    }
+    Ok(())
+}
+
+fn pressure_limit() -> Option<u8> {
+    let directory_policies = config::get().policy.values().filter_map(|rule| parse_keep_policy(rule.keep.as_deref()?).ok());
+    std::iter::once(keep_policy())
+        .chain(directory_policies)
+        .filter_map(|policy| match policy {
+            KeepPolicy::UntilFull(percent) => Some(percent),
+            _ => None,
+        })
+        .min()
+}
+
+fn device(dir: &Path) -> Option<u64> {
+    dir.ancestors().find_map(|dir| dir.metadata().ok()).map(|meta| meta.dev())
+}
+
+fn fullest_trash_volume() -> Option<(PathBuf, f64)> {
+    let mut volumes = HashMap::new();
+    for trash in trash_roots().ok()? {
+        let Some(dev) = device(&trash) else { continue };
+        if let (Entry::Vacant(entry), Ok(usage)) = (volumes.entry(dev), disk_usage(trash.ancestors().find(|dir| dir.exists()).unwrap_or(&trash))) {
+            entry.insert((trash, usage.used_percent(0)));
+        }
+    }
+    volumes.into_values().max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+pub fn warn_near_limit() {
+    let Some(limit) = pressure_limit() else { return };
+    let Some((trash, used)) = fullest_trash_volume() else { return };
+    let share = used * 100.0 / f64::from(limit);
+    let level = WARNING_LEVELS.iter().copied().find(|level| share >= f64::from(*level)).unwrap_or(0);
+    let Ok(state) = state_dir().map(|dir| dir.join(LEVEL_FILE)) else { return };
+    let last: u8 = fs::read_to_string(&state).ok().and_then(|text| text.trim().parse().ok()).unwrap_or(0);
+    if level != last {
+        let _ = fs::write(&state, level.to_string());
+    }
+    if level <= last {
+        return;
+    }
+    match level {
+        100 => println!("Warning: the volume of {} is {used:.0}% full, past the until-{limit}%-full limit; auto-clean now deletes the oldest items", trash.display()),
+        _ => println!("Warning: the volume of {} is {used:.0}% full, {share:.0}% of the until-{limit}%-full limit; past it auto-clean deletes the oldest items", trash.display()),
+    }
+}
+
+pub fn cleanup_old_trash() -> Result<()> {
+    let items = load_trash_items()?;
+    apply_keep_policy(&items, true)?;
+    schedule::record_cleanup()
+}
+
+pub fn autoclean(dry_run: bool) -> Result<()> {
+    let items = load_trash_items()?;
+    if dry_run {
+        return report_expired(&items);
+    }
+    if apply_keep_policy(&items, io::stdin().is_terminal())?.0 == 0 {
+        println!("Nothing to clean");
+    }
+    schedule::record_cleanup()
+}
+
+pub fn is_expired(item: &TrashItem, policy: &KeepPolicy) -> bool {
+    let cutoff = match policy {
+        KeepPolicy::Never | KeepPolicy::UntilFull(_) => return false,
+        KeepPolicy::Keep(span) => span.before(sandbox::now()),
+        KeepPolicy::AskBeforeDelete => sandbox::now() - Duration::days(30),
+    };
+    item.deletion_date < cutoff
+}
+
+pub fn expired_items(items: &[TrashItem]) -> Vec<(&TrashItem, KeepPolicy)> {
+    let (mut expired, pressure): (Vec<_>, Vec<_>) = items
+        .iter()
+        .filter(|item| {
+            let keep = never_expires(&item.original_path);
+            if keep {
+                debug!("{}: matches never_expire", item.original_path.display());
+            }
+            !keep
+        })
+        .map(|item| (item, script::policy_for_item(item).unwrap_or_else(|| policy_for(&item.original_path))))
+        .filter(|(item, policy)| {
+            let expired = matches!(policy, KeepPolicy::UntilFull(_)) || is_expired(item, policy);
+            debug!("{}: policy {policy}, candidate for expiry: {expired}", item.original_path.display());
+            expired
+        })
+        .partition(|(_, policy)| !matches!(policy, KeepPolicy::UntilFull(_)));
+    if pressure.is_empty() {
+        return expired;
+    }
+
+    let mut volumes = HashMap::new();
+    let mut oldest_first = pressure;
+    oldest_first.sort_by(|(a, _), (b, _)| trash_order(a, b));
+    for (item, policy) in oldest_first {
+        let KeepPolicy::UntilFull(percent) = policy else { continue };
+        let Some(dev) = device(item.trash_dir()) else { continue };
+        let (usage, freed) = match volumes.entry(dev) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let Ok(usage) = disk_usage(item.trash_dir()) else { continue };
+                let freed: u64 = expired.iter().filter(|(i, _)| device(i.trash_dir()) == Some(dev)).filter_map(|(i, _)| i.size).sum();
+                entry.insert((usage, freed))
+            }
+        };
+        if usage.used_percent(*freed) < f64::from(percent) {
+            continue;
+        }
+        *freed += item.size.unwrap_or(0);
+        expired.push((item, policy));
+    }
+    expired
+}
+
+fn report_expired(items: &[TrashItem]) -> Result<()> {
+    let compress = compression_candidates(items);
+    if !compress.is_empty() {
+        let total: u64 = compress.iter().filter_map(|i| i.size).sum();
+        println!("Would compress {} aged items ({})", compress.len(), human_size(total));
+    }
+    let expired = expired_items(items);
+    if expired.is_empty() {
+        println!("Auto-clean ({}) would not delete anything", keep_policy());
+        return Ok(());
+    }
+    let total: u64 = expired.iter().filter_map(|(i, _)| i.size).sum();
+    let action = match (archive_dir(), s3_config()) {
+        (Some(dir), _) => format!("archive to {} and remove", dir.display()),
+        (None, Some(s3)) => format!("upload to s3://{} and remove", s3.bucket),
+        (None, None) => "permanently delete".to_owned(),
+    };
+    println!(
+        "Auto-clean ({}) would {action} {} items ({}):",
+        keep_policy(),
+        expired.len(),
+        human_size(total)
+    );
+    println!("   Age     Size  Original Path");
+    for (item, policy) in expired {
+        let age = (sandbox::now() - item.deletion_date).num_days();
+        let size = item.size.map(human_size).unwrap_or_else(|| "-".to_owned());
+        let scripted = script::policy_for_item(item).is_some();
+        let rule = match directory_rule(&item.original_path) {
+            _ if scripted => format!("  [policy script: {policy}]"),
+            Some((dir, _)) => format!("  [{dir}: {policy}]"),
+            None if policy == KeepPolicy::AskBeforeDelete => "  [after confirmation]".to_owned(),
+            None => String::new(),
+        };
+        println!("{age:>5}d  {size:>7}  {}{rule}", item.original_path.display());
+    }
+    Ok(())
+}
+
+fn expire(items: &[&TrashItem]) -> Result<(usize, u64)> {
+    if let Some(archive) = archive_items(items)? {
+        println!("Archived {} expired items to {}", items.len(), archive.display());
+    }
+    let items = upload_items(items);
+    let mut deleted = 0;
+    let mut bytes = 0;
+    for item in items {
+        if purge_item(item).is_ok() {
+            deleted += 1;
+            bytes += item.size.unwrap_or(0);
+        }
+    }
+    Ok((deleted, bytes))
+}
+
+pub fn apply_keep_policy(items: &[TrashItem], interactive: bool) -> Result<(usize, u64)> {
+    let mut automatic = Vec::new();
+    let mut ask = Vec::new();
+    for (item, policy) in expired_items(items) {
+        if policy == KeepPolicy::AskBeforeDelete {
+            ask.push(item);
+        } else {
+            automatic.push(item);
+        }
+    }
+
+    let (mut deleted, mut bytes) = expire(&automatic)?;
+    if deleted > 0 {
+        println!("Auto-cleaned {deleted} expired items");
+    }
+
+    if interactive && !ask.is_empty() && confirm(&format!("{old_len} old items found. Permanently delete them? [y/N] ", old_len = ask.len())) {
+        let (confirmed, confirmed_bytes) = expire(&ask)?;
+        println!("Permanently deleted {confirmed} old items.");
+        deleted += confirmed;
+        bytes += confirmed_bytes;
+    }
+    notify_auto_clean(deleted, bytes);
+    compress_aged(items);
+    Ok((deleted, bytes))
+}
@@ -0,0 +1,182 @@
+// src/s3.rs - Uploads expired items to an S3-compatible bucket and retrieves them back
+use crate::archive::{import_archive, write_archive};
+use crate::config::{self, S3Config};
+use crate::trash::{find_trash_dir, TrashItem};
+use anyhow::{anyhow, Context, Result};
+use nanoid::nanoid;
+use std::fs;
+use tracing::warn;
+
+pub fn s3_config() -> Option<&'static S3Config> {
+    config::get().s3.as_ref()
+}
+
+fn object_key(s3: &S3Config, name: &str) -> String {
+    format!("{}{name}.tar.zst", s3.prefix.as_deref().unwrap_or(""))
+}
+
+pub fn upload_items<'a>(items: &[&'a TrashItem]) -> Vec<&'a TrashItem> {
+    let Some(s3) = s3_config() else {
+        return items.to_vec();
+    };
+    let mut uploaded = Vec::new();
+    for item in items {
+        match upload_item(s3, item) {
+            Ok(()) => uploaded.push(*item),
+            Err(e) => warn!("{}: upload failed, keeping it in the trash: {e:#}", item.trashed_name),
+        }
+    }
+    if !uploaded.is_empty() {
+        println!("Uploaded {} expired items to s3://{}", uploaded.len(), s3.bucket);
+    }
+    uploaded
+}
+
+fn upload_item(s3: &S3Config, item: &TrashItem) -> Result<()> {
+    let temp = find_trash_dir()?.join(format!(".rip-upload-{}.tar.zst", nanoid!(8)));
+    let result = write_archive(&temp, &[item]).and_then(|_| backend::put(s3, &object_key(s3, &item.trashed_name), &temp));
+    let _ = fs::remove_file(&temp);
+    result
+}
+
+pub fn retrieve(name: Option<&str>) -> Result<()> {
+    let s3 = s3_config().ok_or_else(|| anyhow!("No S3 bucket configured (add an [s3] section to the config)"))?;
+    let prefix = s3.prefix.as_deref().unwrap_or("");
+    let Some(name) = name else {
+        let keys = backend::list(s3, prefix)?;
+        if keys.is_empty() {
+            println!("No archived items in s3://{}/{prefix}", s3.bucket);
+        }
+        for key in keys {
+            let name = key.strip_prefix(prefix).unwrap_or(&key);
+            println!("{}", name.strip_suffix(".tar.zst").unwrap_or(name));
+        }
+        return Ok(());
+    };
+
+    let temp = find_trash_dir()?.join(format!(".rip-retrieve-{}.tar.zst", nanoid!(8)));
+    let result = backend::get(s3, &object_key(s3, name), &temp).and_then(|_| import_archive(&temp));
+    let _ = fs::remove_file(&temp);
+    let imported = result.with_context(|| format!("Cannot retrieve {name}"))?;
+    println!("Retrieved {imported} items from s3://{} into the trash", s3.bucket);
+    Ok(())
+}
+
+#[cfg(feature = "s3")]
+mod backend {
+    use crate::config::S3Config;
+    use anyhow::{anyhow, Context, Result};
+    use chrono::Utc;
+    use hmac::{Hmac, Mac};
+    use sha2::{Digest, Sha256};
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+    use urlencoding::encode;
+
+    const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+    fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn credentials(s3: &S3Config) -> Result<(String, String)> {
+        let access = s3.access_key.clone().or_else(|| std::env::var("AWS_ACCESS_KEY_ID").ok());
+        let secret = s3.secret_key.clone().or_else(|| std::env::var("AWS_SECRET_ACCESS_KEY").ok());
+        access
+            .zip(secret)
+            .ok_or_else(|| anyhow!("S3 credentials missing (set access_key/secret_key or AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY)"))
+    }
+
+    fn request(s3: &S3Config, method: &str, key: &str, query: &str) -> Result<ureq::Request> {
+        let (access, secret) = credentials(s3)?;
+        let endpoint = s3.endpoint.trim_end_matches('/');
+        let host = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+        let region = s3.region.as_deref().unwrap_or("us-east-1");
+        let path: String = std::iter::once(s3.bucket.as_str())
+            .chain(key.split('/'))
+            .map(|segment| format!("/{}", encode(segment)))
+            .collect();
+        let path = if key.is_empty() { format!("/{}", encode(&s3.bucket)) } else { path };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let canonical = format!(
+            "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{UNSIGNED_PAYLOAD}\nx-amz-date:{amz_date}\n\nhost;x-amz-content-sha256;x-amz-date\n{UNSIGNED_PAYLOAD}"
+        );
+        let scope = format!("{date}/{region}/s3/aws4_request");
+        let to_sign = format!("AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}", hex::encode(Sha256::digest(canonical)));
+        let key = ["s3", "aws4_request"].iter().fold(
+            hmac(&hmac(format!("AWS4{secret}").as_bytes(), &date), region),
+            |key, part| hmac(&key, part),
+        );
+        let signature = hex::encode(hmac(&key, &to_sign));
+
+        let url = if query.is_empty() { format!("{endpoint}{path}") } else { format!("{endpoint}{path}?{query}") };
+        Ok(ureq::request(method, &url)
+            .set("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .set("x-amz-date", &amz_date)
+            .set(
+                "Authorization",
+                &format!("AWS4-HMAC-SHA256 Credential={access}/{scope}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}"),
+            ))
+    }
+
+    pub fn put(s3: &S3Config, key: &str, src: &Path) -> Result<()> {
+        let file = File::open(src)?;
+        let len = file.metadata()?.len();
+        request(s3, "PUT", key, "")?
+            .set("Content-Length", &len.to_string())
+            .send(file)
+            .with_context(|| format!("PUT {key} failed"))?;
+        Ok(())
+    }
+
+    pub fn get(s3: &S3Config, key: &str, dest: &Path) -> Result<()> {
+        let response = request(s3, "GET", key, "")?
+            .call()
+            .with_context(|| format!("GET {key} failed"))?;
+        let mut file = File::create(dest)?;
+        io::copy(&mut response.into_reader(), &mut file)?;
+        Ok(())
+    }
+
+    pub fn list(s3: &S3Config, prefix: &str) -> Result<Vec<String>> {
+        let query = format!("list-type=2&prefix={}", encode(prefix));
+        let body = request(s3, "GET", "", &query)?
+            .call()
+            .context("Listing the bucket failed")?
+            .into_string()?;
+        Ok(body
+            .split("<Key>")
+            .skip(1)
+            .filter_map(|rest| rest.split_once("</Key>").map(|(key, _)| key.replace("&amp;", "&")))
+            .collect())
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+mod backend {
+    use crate::config::S3Config;
+    use anyhow::{anyhow, Result};
+    use std::path::Path;
+
+    fn unsupported() -> anyhow::Error {
+        anyhow!("rip was built without S3 support (rebuild with --features s3)")
+    }
+
+    pub fn put(_s3: &S3Config, _key: &str, _src: &Path) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn get(_s3: &S3Config, _key: &str, _dest: &Path) -> Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn list(_s3: &S3Config, _prefix: &str) -> Result<Vec<String>> {
+        Err(unsupported())
+    }
+}
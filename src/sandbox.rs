@@ -0,0 +1,100 @@
+// src/sandbox.rs - Throwaway trash and sample files for trying rip out, with an adjustable clock
+use crate::duration::Span;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+const SANDBOX_ENV: &str = "RIP_SANDBOX";
+const CLOCK_FILE: &str = "clock";
+const SAMPLE_FILES: &[(&str, &str)] = &[
+    ("notes.txt", "Shopping: milk, eggs, coffee\n"),
+    ("report-draft.md", "# Quarterly report\n\nTo be written.\n"),
+    ("photos/beach.jpg", "not really a photo\n"),
+    ("photos/sunset.jpg", "not really a photo either\n"),
+    ("build/output.o", "\x7fELF pretend object file\n"),
+];
+
+fn clock_offset() -> i64 {
+    static OFFSET: OnceLock<i64> = OnceLock::new();
+    *OFFSET.get_or_init(|| {
+        env::var_os(SANDBOX_ENV)
+            .and_then(|dir| fs::read_to_string(Path::new(&dir).join(CLOCK_FILE)).ok())
+            .and_then(|offset| offset.trim().parse().ok())
+            .unwrap_or(0)
+    })
+}
+
+pub fn now() -> DateTime<Utc> {
+    Utc::now() + Duration::seconds(clock_offset())
+}
+
+pub fn advance_clock(span: &str) -> Result<()> {
+    let dir = env::var_os(SANDBOX_ENV).context("--sandbox-clock only works inside rip --sandbox")?;
+    let span = Span::parse(span)?;
+    let current = now();
+    let too_far = || anyhow!("Cannot move the sandbox clock forward by {span}: too far");
+    let earlier = Some(span.before(current)).filter(|t| *t > DateTime::<Utc>::MIN_UTC).ok_or_else(too_far)?;
+    let offset = clock_offset() + (current - earlier).num_seconds();
+    let moved = Duration::try_seconds(offset).and_then(|offset| Utc::now().checked_add_signed(offset)).ok_or_else(too_far)?;
+    fs::write(Path::new(&dir).join(CLOCK_FILE), offset.to_string())?;
+    println!("Sandbox clock moved forward by {span}; it is now {}", moved.format("%Y-%m-%d %H:%M"));
+    Ok(())
+}
+
+fn prepare(root: &Path) -> Result<()> {
+    let scratch = root.join("scratch");
+    for (name, content) in SAMPLE_FILES {
+        let path = scratch.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+    for dir in ["trash", "config/rip", "state", "bin"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    fs::write(root.join("config/rip/config.toml"), "keep = \"7d\"\n")?;
+    let exe = env::current_exe().context("Cannot locate the rip executable")?;
+    std::os::unix::fs::symlink(exe, root.join("bin/rip"))?;
+    Ok(())
+}
+
+pub fn run_sandbox() -> Result<()> {
+    if env::var_os(SANDBOX_ENV).is_some() {
+        return Err(anyhow!("already inside a rip sandbox"));
+    }
+    let root = env::temp_dir().join(format!("rip-sandbox-{}", nanoid::nanoid!(6)));
+    fs::create_dir(&root).with_context(|| format!("Cannot create {}", root.display()))?;
+    let result = prepare(&root).and_then(|()| enter(&root));
+    if let Err(e) = fs::remove_dir_all(&root) {
+        eprintln!("rip: could not remove the sandbox {}: {e}", root.display());
+    }
+    result
+}
+
+fn enter(root: &Path) -> Result<()> {
+    let shell = env::var_os("SHELL").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/bin/sh"));
+    let path = env::var_os("PATH").unwrap_or_default();
+    let path = env::join_paths(std::iter::once(root.join("bin")).chain(env::split_paths(&path)))?;
+    println!("Sandbox ready in {}: your real trash and files are not touched.", root.display());
+    println!("  sample files: {} (the shell starts here)", root.join("scratch").display());
+    println!("  trash:        {} (keep policy: 7 days)", root.join("trash").display());
+    println!("Try: rip notes.txt photos   rip --list   rip --restore 1   rip --empty");
+    println!("     rip --sandbox-clock 10d, then rip --autoclean -n, to see the policy expire items");
+    println!("Type exit to leave; the sandbox is deleted afterwards.");
+    let status = Command::new(&shell)
+        .current_dir(root.join("scratch"))
+        .env(SANDBOX_ENV, root)
+        .env("RIP_TRASH_DIR", root.join("trash"))
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("XDG_STATE_HOME", root.join("state"))
+        .env("PATH", path)
+        .status()
+        .with_context(|| format!("Cannot start {}", shell.display()))?;
+    println!("Left the sandbox{}", if status.success() { "" } else { " (the shell exited with an error)" });
+    Ok(())
+}
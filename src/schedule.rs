@@ -0,0 +1,123 @@
+// src/schedule.rs - Cron-style schedules deciding when auto-clean is due
+use crate::config;
+use crate::trash::state_dir;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn parse_field(spec: &str, min: u32, max: u32) -> Result<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u32>().map_err(|_| anyhow!("Invalid step: {part}"))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(anyhow!("Invalid step: {part}"));
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                a.parse().map_err(|_| anyhow!("Invalid range: {part}"))?,
+                b.parse().map_err(|_| anyhow!("Invalid range: {part}"))?,
+            )
+        } else {
+            let v = range.parse().map_err(|_| anyhow!("Invalid value: {part}"))?;
+            (v, if part.contains('/') { max } else { v })
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("Value out of range {min}-{max}: {part}"));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            allowed[v as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+impl Schedule {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!("Expected 5 fields (minute hour day month weekday): {expr}"));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+        Ok(Schedule {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    pub fn matches(&self, t: &DateTime<Local>) -> bool {
+        let day = self.days[t.day() as usize];
+        let weekday = self.weekdays[t.weekday().num_days_from_sunday() as usize];
+        let day_ok = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        };
+        self.minutes[t.minute() as usize]
+            && self.hours[t.hour() as usize]
+            && self.months[t.month() as usize]
+            && day_ok
+    }
+
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        for _ in 0..366 * 24 * 60 {
+            if self.matches(&t) {
+                return Some(t);
+            }
+            t += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+fn last_run() -> Option<DateTime<Utc>> {
+    let text = fs::read_to_string(state_dir().ok()?.join("last-autoclean")).ok()?;
+    DateTime::parse_from_rfc3339(text.trim()).ok().map(|t| t.with_timezone(&Utc))
+}
+
+pub fn has_schedule() -> bool {
+    config::get().schedule.is_some()
+}
+
+pub fn cleanup_due() -> bool {
+    let Some(Ok(schedule)) = config::get().schedule.as_deref().map(Schedule::parse) else {
+        return true;
+    };
+    match last_run() {
+        None => true,
+        Some(last) => schedule
+            .next_after(last.with_timezone(&Local))
+            .is_some_and(|next| next <= Local::now()),
+    }
+}
+
+pub fn record_cleanup() -> Result<()> {
+    fs::write(state_dir()?.join("last-autoclean"), Utc::now().to_rfc3339())?;
+    Ok(())
+}
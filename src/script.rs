@@ -0,0 +1,104 @@
+// src/script.rs - User policy scripts (Rhai) deciding per-item expiration
+use crate::config;
+
+pub fn script_path() -> Option<std::path::PathBuf> {
+    config::get().policy_script.as_deref().map(config::expand_path)
+}
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use super::script_path;
+    use crate::duration::Span;
+    use crate::policy::{parse_keep_policy, KeepPolicy};
+    use crate::sandbox;
+    use crate::trash::TrashItem;
+    use rhai::{Dynamic, Engine, Map, Scope, AST};
+    use tracing::{debug, warn};
+
+    thread_local! {
+        static SCRIPT: Option<(Engine, AST)> = compile();
+    }
+
+    fn compile() -> Option<(Engine, AST)> {
+        let path = script_path()?;
+        let mut engine = Engine::new();
+        engine.set_max_operations(100_000);
+        match engine.compile_file(path.clone()) {
+            Ok(ast) => Some((engine, ast)),
+            Err(e) => {
+                warn!("{}: {e}; falling back to the configured keep policy", path.display());
+                None
+            }
+        }
+    }
+
+    fn item_map(item: &TrashItem) -> Map {
+        let age = sandbox::now() - item.deletion_date;
+        let is_dir = item.trashed_path().symlink_metadata().is_ok_and(|m| m.is_dir());
+        let mut map = Map::new();
+        map.insert("path".into(), item.original_path.to_string_lossy().into_owned().into());
+        map.insert("name".into(), item.trashed_name.clone().into());
+        map.insert("size".into(), item.size.map_or(Dynamic::UNIT, |s| Dynamic::from(s as i64)));
+        map.insert("age_days".into(), age.num_days().into());
+        map.insert("age_hours".into(), age.num_hours().into());
+        map.insert("is_dir".into(), is_dir.into());
+        map.insert("deleted".into(), item.deletion_date.to_rfc3339().into());
+        map
+    }
+
+    fn decide(result: Dynamic) -> Result<Option<KeepPolicy>, String> {
+        if result.is_unit() {
+            return Ok(None);
+        }
+        if let Some(expire) = result.clone().try_cast::<bool>() {
+            return Ok(Some(if expire { KeepPolicy::Keep(Span::Minutes(0)) } else { KeepPolicy::Never }));
+        }
+        if let Ok(policy) = result.clone().into_string() {
+            return parse_keep_policy(&policy).map(Some).map_err(|e| e.to_string());
+        }
+        Err(format!("expire() returned {}, expected true, false, a keep policy string or ()", result.type_name()))
+    }
+
+    pub fn policy_for_item(item: &TrashItem) -> Option<KeepPolicy> {
+        SCRIPT.with(|script| {
+            let (engine, ast) = script.as_ref()?;
+            let mut scope = Scope::new();
+            let home = dirs_next::home_dir().unwrap_or_default();
+            scope.push_constant("HOME", home.to_string_lossy().into_owned());
+            let result = engine
+                .call_fn::<Dynamic>(&mut scope, ast, "expire", (item_map(item),))
+                .map_err(|e| e.to_string())
+                .and_then(decide);
+            match result {
+                Ok(policy) => {
+                    debug!("{}: policy script decided {policy:?}", item.original_path.display());
+                    policy
+                }
+                Err(e) => {
+                    warn!("{}: policy script failed: {e}", item.original_path.display());
+                    None
+                }
+            }
+        })
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+mod engine {
+    use super::script_path;
+    use crate::policy::KeepPolicy;
+    use crate::trash::TrashItem;
+    use std::sync::Once;
+    use tracing::warn;
+
+    static WARNED: Once = Once::new();
+
+    pub fn policy_for_item(_item: &TrashItem) -> Option<KeepPolicy> {
+        if script_path().is_some() {
+            WARNED.call_once(|| warn!("rip was built without scripting support (rebuild with --features scripting); ignoring policy_script"));
+        }
+        None
+    }
+}
+
+pub use engine::policy_for_item;
@@ -0,0 +1,103 @@
+// src/shell.rs - Shell integration snippets printed by rip init (rm wrapper, unrm, trash count in the prompt)
+use crate::trash::find_trash_dir;
+use anyhow::Result;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+const POSIX_RM: &str = r#"rm() {
+    local args=() opts=1 a
+    for a in "$@"; do
+        if [ "$opts" = 1 ]; then
+            case "$a" in
+                --) opts=0; continue ;;
+                -?*) continue ;;
+            esac
+        fi
+        args+=("$a")
+    done
+    command rip -- "${args[@]}"
+}
+
+unrm() {
+    command rip --restore "${1:-1}"
+}
+"#;
+
+const BASH: &str = r#"__rip_prompt() {
+    local items=({INFO}/*.trashinfo)
+    [ -e "${items[0]}" ] && printf '[trash:%d] ' "${#items[@]}"
+}
+
+case "$PS1" in
+    *__rip_prompt*) ;;
+    *) PS1='$(__rip_prompt)'"$PS1" ;;
+esac
+"#;
+
+const ZSH: &str = r#"__rip_prompt() {
+    local items=({INFO}/*.trashinfo(N))
+    (( ${#items} )) && printf '[trash:%d] ' "${#items}"
+}
+
+setopt prompt_subst
+[[ $PROMPT == *__rip_prompt* ]] || PROMPT='$(__rip_prompt)'"$PROMPT"
+"#;
+
+const FISH: &str = r#"function rm --description 'Move files to the trash with rip'
+    set -l args
+    set -l opts 1
+    for a in $argv
+        if test $opts = 1
+            switch $a
+                case --
+                    set opts 0
+                    continue
+                case '-?*'
+                    continue
+            end
+        end
+        set -a args $a
+    end
+    command rip -- $args
+end
+
+function unrm --description 'Restore the Nth newest trashed item (default: 1)'
+    set -l n 1
+    set -q argv[1]; and set n $argv[1]
+    command rip --restore $n
+end
+
+function __rip_prompt
+    set -l items {INFO}/*.trashinfo
+    set -q items[1]; and printf '[trash:%d] ' (count $items)
+end
+
+if not functions -q __rip_fish_prompt
+    functions -c fish_prompt __rip_fish_prompt
+    function fish_prompt
+        __rip_prompt
+        __rip_fish_prompt
+    end
+end
+"#;
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+pub fn print_init(shell: Shell) -> Result<()> {
+    let info = quote(&find_trash_dir()?.join("info").to_string_lossy());
+    let script = match shell {
+        Shell::Bash => format!("{POSIX_RM}\n{BASH}"),
+        Shell::Zsh => format!("{POSIX_RM}\n{ZSH}"),
+        Shell::Fish => FISH.to_owned(),
+    };
+    print!("{}", script.replace("{INFO}", &info));
+    Ok(())
+}
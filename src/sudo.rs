@@ -0,0 +1,96 @@
+// src/sudo.rs - Uses the invoking user's trash (and hands files over to them) when run under sudo; user lookups
+use crate::config;
+use std::env;
+use std::ffi::{CStr, CString, OsStr};
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing::debug;
+
+pub struct SudoUser {
+    pub uid: u32,
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+fn lookup() -> Option<SudoUser> {
+    if unsafe { libc::geteuid() } != 0 || config::root_trash() {
+        return None;
+    }
+    let uid: u32 = env::var("SUDO_UID").ok()?.parse().ok()?;
+    if uid == 0 {
+        return None;
+    }
+    let pw = unsafe { libc::getpwuid(uid) };
+    if pw.is_null() {
+        return None;
+    }
+    let (gid, home) = unsafe { ((*pw).pw_gid, CStr::from_ptr((*pw).pw_dir)) };
+    let user = SudoUser { uid, gid, home: PathBuf::from(OsStr::from_bytes(home.to_bytes())) };
+    debug!("running under sudo, using the trash of uid {uid} in {}", user.home.display());
+    Some(user)
+}
+
+pub fn invoking_user() -> Option<&'static SudoUser> {
+    static USER: OnceLock<Option<SudoUser>> = OnceLock::new();
+    USER.get_or_init(lookup).as_ref()
+}
+
+pub fn trash_uid() -> u32 {
+    invoking_user().map_or_else(|| unsafe { libc::getuid() }, |user| user.uid)
+}
+
+pub fn acting_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+pub fn may_act_for(uid: u32) -> bool {
+    let me = acting_uid();
+    me == 0 || me == uid
+}
+
+pub fn user_name(uid: u32) -> String {
+    let pw = unsafe { libc::getpwuid(uid) };
+    match pw.is_null() {
+        true => uid.to_string(),
+        false => unsafe { CStr::from_ptr((*pw).pw_name) }.to_string_lossy().into_owned(),
+    }
+}
+
+pub fn user_id(name: &str) -> Option<u32> {
+    if let Ok(uid) = name.parse() {
+        return Some(uid);
+    }
+    let c_name = CString::new(name).ok()?;
+    let pw = unsafe { libc::getpwnam(c_name.as_ptr()) };
+    (!pw.is_null()).then(|| unsafe { (*pw).pw_uid })
+}
+
+pub fn chown_to_user(path: &Path) {
+    if let Some(user) = invoking_user() {
+        let _ = std::os::unix::fs::lchown(path, Some(user.uid), Some(user.gid));
+    }
+}
+
+pub fn hand_over(path: &Path) {
+    let Some(user) = invoking_user() else { return };
+    if path.parent().and_then(|p| p.metadata().ok()).is_some_and(|m| m.uid() == user.uid) {
+        chown_to_user(path);
+    }
+}
+
+pub fn create_dir_all(dir: &Path) -> io::Result<()> {
+    let missing: Vec<PathBuf> = dir
+        .ancestors()
+        .take_while(|d| d.symlink_metadata().is_err())
+        .map(Path::to_path_buf)
+        .collect();
+    let created = fs::create_dir_all(dir);
+    for created in missing.iter().rev().filter(|d| d.exists()) {
+        hand_over(created);
+    }
+    created
+}
@@ -0,0 +1,36 @@
+// src/sync.rs - Mirrors trashed items to a remote replica with rsync over ssh
+use crate::config;
+use crate::trash::find_trash_dir;
+use anyhow::{anyhow, Context, Result};
+use std::process::Command;
+use tracing::warn;
+
+pub fn sync_target() -> Option<&'static str> {
+    config::get().sync.as_deref()
+}
+
+pub fn replicate() -> Result<()> {
+    let Some(target) = sync_target() else {
+        return Err(anyhow!("No sync target configured (set sync = \"host:path\" in the config)"));
+    };
+    let trash = find_trash_dir()?;
+    let status = Command::new("rsync")
+        .args(["-a", "--ignore-existing", "--exclude", ".rip-*"])
+        .arg(trash.join("files"))
+        .arg(trash.join("info"))
+        .arg(format!("{}/", target.trim_end_matches('/')))
+        .status()
+        .context("Cannot run rsync")?;
+    if !status.success() {
+        return Err(anyhow!("rsync to {target} failed ({status})"));
+    }
+    Ok(())
+}
+
+pub fn replicate_if_configured() {
+    if sync_target().is_some() {
+        if let Err(e) = replicate() {
+            warn!("sync: {e}");
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// src/systemd.rs - Generates user-level systemd units that run auto-clean daily
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+
+const SERVICE_NAME: &str = "rip-autoclean.service";
+const TIMER_NAME: &str = "rip-autoclean.timer";
+
+pub fn install_timer() -> Result<()> {
+    let unit_dir = dirs_next::config_dir()
+        .context("Cannot determine config directory")?
+        .join("systemd/user");
+    fs::create_dir_all(&unit_dir)
+        .with_context(|| format!("Cannot create {}", unit_dir.display()))?;
+
+    let exe = env::current_exe().context("Cannot locate the rip executable")?;
+    let service = format!(
+        "[Unit]\nDescription=Apply rip trash auto-clean policy\n\n\
+         [Service]\nType=oneshot\nExecStart={} --autoclean\n",
+        exe.display()
+    );
+    let timer = format!(
+        "[Unit]\nDescription=Run rip auto-clean daily\n\n\
+         [Timer]\nOnCalendar=daily\nPersistent=true\nUnit={SERVICE_NAME}\n\n\
+         [Install]\nWantedBy=timers.target\n"
+    );
+
+    let service_path = unit_dir.join(SERVICE_NAME);
+    let timer_path = unit_dir.join(TIMER_NAME);
+    fs::write(&service_path, service)?;
+    fs::write(&timer_path, timer)?;
+
+    println!("Wrote {}", service_path.display());
+    println!("Wrote {}", timer_path.display());
+    println!("Enable it with:");
+    println!("  systemctl --user daemon-reload");
+    println!("  systemctl --user enable --now {TIMER_NAME}");
+    Ok(())
+}
@@ -0,0 +1,135 @@
+// src/template.rs - Output templates for --list --format ('{index}\t{date:%F}\t{size}\t{path}')
+use crate::fs_utils::human_size;
+use crate::sudo;
+use crate::trash::TrashItem;
+use anyhow::{anyhow, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::Local;
+
+const FIELDS: &[&str] = &["index", "date", "size", "bytes", "path", "name", "location", "trash", "type", "tags", "note", "user"];
+const DEFAULT_DATE: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy)]
+enum Align {
+    Left(usize),
+    Right(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Date(String),
+    Field(&'static str, Option<Align>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Template(Vec<Piece>);
+
+fn parse_align(field: &str, spec: &str) -> Result<Align> {
+    let invalid = || anyhow!("Invalid format for {{{field}}}: {spec:?} (use <N or >N)");
+    let (align, width): (fn(usize) -> Align, &str) = match spec.split_at_checked(1).ok_or_else(invalid)? {
+        ("<", width) => (Align::Left, width),
+        (">", width) => (Align::Right, width),
+        _ => (Align::Right, spec),
+    };
+    width.parse().map(align).map_err(|_| invalid())
+}
+
+fn field(name: &str, spec: Option<&str>) -> Result<Piece> {
+    let Some(&name) = FIELDS.iter().find(|f| **f == name) else {
+        return Err(anyhow!("Unknown field {{{name}}} in --format; available: {}", FIELDS.join(", ")));
+    };
+    if name == "date" {
+        let spec = spec.unwrap_or(DEFAULT_DATE);
+        if StrftimeItems::new(spec).any(|item| matches!(item, Item::Error)) {
+            return Err(anyhow!("Invalid date format {spec:?} (strftime, e.g. %F or %Y-%m-%d %H:%M)"));
+        }
+        return Ok(Piece::Date(spec.to_owned()));
+    }
+    Ok(Piece::Field(name, spec.map(|spec| parse_align(name, spec)).transpose()?))
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('0') => literal.push('\0'),
+                    Some(other) => literal.push(other),
+                    None => literal.push('\\'),
+                },
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| anyhow!("Unclosed {{ in --format {text:?}"))?;
+                    let (name, spec) = match rest[..end].split_once(':') {
+                        Some((name, spec)) => (name, Some(spec)),
+                        None => (&rest[..end], None),
+                    };
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(field(name.trim(), spec)?);
+                    chars = rest[end + 1..].chars();
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Ok(Template(pieces))
+    }
+
+    pub fn render(&self, index: usize, item: &TrashItem) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            let (value, align) = match piece {
+                Piece::Text(text) => {
+                    out.push_str(text);
+                    continue;
+                }
+                Piece::Date(spec) => {
+                    out.push_str(&item.deletion_date.with_timezone(&Local).format(spec).to_string());
+                    continue;
+                }
+                Piece::Field(name, align) => (value(name, index, item), align),
+            };
+            match align {
+                None => out.push_str(&value),
+                Some(Align::Left(width)) => out.push_str(&format!("{value:<width$}")),
+                Some(Align::Right(width)) => out.push_str(&format!("{value:>width$}")),
+            }
+        }
+        out
+    }
+}
+
+fn value(name: &str, index: usize, item: &TrashItem) -> String {
+    match name {
+        "index" => index.to_string(),
+        "size" => item.size.map(human_size).unwrap_or_else(|| "-".to_owned()),
+        "bytes" => item.size.map(|size| size.to_string()).unwrap_or_default(),
+        "path" => item.original_path.display().to_string(),
+        "name" => item.trashed_name.clone(),
+        "location" => item.trashed_path().display().to_string(),
+        "trash" => item.trash_dir().display().to_string(),
+        "type" => item.kind.clone().unwrap_or_default(),
+        "tags" => item.tags.join(","),
+        "note" => item.note.clone().unwrap_or_default(),
+        "user" => item.trashed_by.map(sudo::user_name).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
@@ -1,13 +1,43 @@
-// src/trash.rs - Core trash implementation with symlink safety and configurable auto-clean policies
-use crate::fs_utils::{copy_recursively, remove_recursively};
+// src/trash.rs - Core trash implementation with symlink safety
+use crate::audit;
+use crate::compress::decompress_to;
+use crate::config;
+use crate::i18n::tr;
+use crate::template::Template;
+use crate::crypto::{decrypt_to, encrypt_into, encryption_enabled, ENCRYPTED_MARKER};
+use crate::ephemeral::{ephemeral_policy, mounted_trash, volatile_reason, Ephemeral};
+use crate::duration::Span;
+use crate::filter::ItemFilter;
+use crate::dedup::{deduplicated, dedup_enabled, file_digest, link_duplicate};
+use crate::fs_utils::{
+    case_insensitive, copy_recursively, copy_verified, disk_usage, human_size, move_across, path_size, preserve_metadata, remove_recursively, rename_inspected,
+    shred_path,
+};
+use crate::hooks;
+use crate::journal;
+use crate::policy::cleanup_old_trash;
+use crate::sandbox;
+use crate::schedule;
+use crate::sudo;
+use crate::volume::{
+    network_fs_policy, path_within_volume, remote_filesystem, trash_topdir, volume_trash_for, volume_trashes, NetworkFs,
+};
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, Duration, Local, Utc, SecondsFormat};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use nanoid::nanoid;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::env;
+use std::ffi::{CString, OsStr, OsString};
 use std::fs;
 use std::io::{self, BufRead, Write};
-use std::path::PathBuf;
-use urlencoding::{decode, encode};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+use urlencoding::{decode, decode_binary, encode};
 
 #[derive(Clone, Debug)]
 pub struct TrashItem {
@@ -15,49 +45,39 @@ pub struct TrashItem {
     pub deletion_date: DateTime<Utc>,
     pub trashed_name: String,
     pub info_path: PathBuf,
+    pub size: Option<u64>,
+    pub compressed: Option<String>,
+    pub encrypted: Option<String>,
+    pub sha256: Option<String>,
+    pub tags: Vec<String>,
+    pub note: Option<String>,
+    pub kind: Option<String>,
+    pub mode: Option<u32>,
+    pub owner: Option<(u32, u32)>,
+    pub selinux: Option<String>,
+    pub capability: Option<Vec<u8>>,
+    pub sequence: Option<u64>,
+    pub inode: Option<(u64, u64)>,
+    pub trashed_by: Option<u32>,
+    pub operation: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum KeepPolicy {
-    Never,
-    Days(i64),
-    AskBeforeDelete,
-}
-
-static mut KEEP_POLICY: KeepPolicy = KeepPolicy::Days(30);
-
-pub fn set_keep_policy(policy: &str) -> Result<()> {
-    let p = policy.trim().to_lowercase();
-    unsafe {
-        KEEP_POLICY = match p.as_str() {
-            "never" => KeepPolicy::Never,
-            "ask" => KeepPolicy::AskBeforeDelete,
-            s if s.ends_with('d') => {
-                let days = s.trim_end_matches('d')
-                    .parse::<i64>()
-                    .map_err(|_| anyhow!("Invalid day count: {s}"))?;
-                if days <= 0 {
-                    KeepPolicy::Never
-                } else {
-                    KeepPolicy::Days(days)
-                }
-            }
-            _ => return Err(anyhow!("Valid policies: ask | never | 30d | 90d | ...")),
-        };
-        println!("Auto-clean policy set to: {KEEP_POLICY:#?}");
+impl TrashItem {
+    pub fn trash_dir(&self) -> &Path {
+        self.info_path.parent().and_then(Path::parent).unwrap_or(Path::new("."))
     }
-    Ok(())
-}
 
-pub fn show_keep_policy() -> Result<()> {
-    unsafe {
-        println!("Current auto-clean policy: {KEEP_POLICY:#?}");
+    pub fn trashed_path(&self) -> PathBuf {
+        self.trash_dir().join("files").join(&self.trashed_name)
     }
-    Ok(())
 }
 
-fn confirm(prompt: &str) -> bool {
+pub fn confirm(prompt: &str) -> bool {
     print!("{prompt}");
+    if config::get().no_confirm == Some(true) {
+        println!("y");
+        return true;
+    }
     let _ = io::stdout().flush();
     io::stdin().lock().lines()
         .next()
@@ -66,64 +86,165 @@ fn confirm(prompt: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn cleanup_old_trash() -> Result<()> {
-    let items = load_trash_items()?;
-    if items.is_empty() {
-        return Ok(());
+pub const SHRED_CAVEAT: &str = "Note: shredding is best-effort. SSDs (wear levelling), copy-on-write \
+filesystems (btrfs, ZFS), snapshots and backups may still hold the old data.";
+
+pub fn shred_item(item: &TrashItem) -> Result<()> {
+    shred_path(&item.trashed_path())?;
+    purge_item(item)
+}
+
+fn remove_trashed(item: &TrashItem) -> Result<bool> {
+    let trashed = item.trashed_path();
+    let is_dir = trashed.symlink_metadata().is_ok_and(|m| m.is_dir());
+    if trashed.symlink_metadata().is_ok() {
+        remove_recursively(&trashed)?;
     }
+    fs::remove_file(&item.info_path)?;
+    Ok(is_dir)
+}
 
-    unsafe {
-        match KEEP_POLICY {
-            KeepPolicy::Never => {}
-            KeepPolicy::Days(days) => {
-                let cutoff = Utc::now() - Duration::days(days);
-                let mut deleted = 0;
-                for item in &items {
-                    if item.deletion_date < cutoff {
-                        let trash = find_trash_dir()?;
-                        let _ = fs::remove_file(trash.join("files").join(&item.trashed_name));
-                        let _ = fs::remove_file(&item.info_path);
-                        deleted += 1;
-                    }
-                }
-                if deleted > 0 {
-                    println!("Auto-cleaned {deleted} items older than {days} days");
-                }
-            }
-            KeepPolicy::AskBeforeDelete => {
-                let cutoff = Utc::now() - Duration::days(30);
-                let old: Vec<_> = items.iter().filter(|i| i.deletion_date < cutoff).collect();
-                if !old.is_empty() && confirm(&format!("{old_len} old items found. Permanently delete them? [y/N] ", old_len = old.len())) {
-                    for item in &old {
-                        let trash = find_trash_dir()?;
-                        let _ = fs::remove_file(trash.join("files").join(&item.trashed_name));
-                        let _ = fs::remove_file(&item.info_path);
-                    }
-                    println!("Permanently deleted {old_len} old items.", old_len = old.len());
-                }
-            }
-        }
+pub fn check_owner(item: &TrashItem) -> Result<()> {
+    match item.trashed_by {
+        Some(uid) if !sudo::may_act_for(uid) => Err(anyhow!(
+            "{} was trashed by {}; only they or root can restore or purge it",
+            item.original_path.display(),
+            sudo::user_name(uid)
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn purge_item(item: &TrashItem) -> Result<()> {
+    check_owner(item)?;
+    let trashed = item.trashed_path();
+    hooks::run("pre_purge", std::slice::from_ref(&trashed))?;
+    if remove_trashed(item)? {
+        forget_directory_size(item.trash_dir(), &item.trashed_name)?;
     }
+    audit::record("purge", &item.original_path, item.size);
+    hooks::run("post_purge", &[trashed])?;
     Ok(())
 }
 
-pub fn find_trash_dir() -> Result<PathBuf> {
-    let trash = if let Ok(xdg) = env::var("XDG_DATA_HOME") {
-        if !xdg.is_empty() {
-            PathBuf::from(xdg).join("Trash")
-        } else {
-            dirs_next::home_dir().unwrap().join(".local/share/Trash")
+pub fn state_dir() -> Result<PathBuf> {
+    let state = match (env::var("XDG_STATE_HOME"), dirs_next::home_dir(), &config::get().trash_dir) {
+        (Ok(xdg), _, _) if !xdg.is_empty() => PathBuf::from(xdg).join("rip"),
+        (_, Some(home), _) => home.join(".local/state/rip"),
+        (_, None, Some(dir)) => config::expand_path(dir).join(".rip-state"),
+        (_, None, None) => return Err(anyhow!("{NO_HOME}")),
+    };
+    fs::create_dir_all(&state)
+        .with_context(|| format!("Cannot create state directory {}", state.display()))?;
+    Ok(state)
+}
+
+fn default_trash_dir() -> Result<PathBuf> {
+    if let Some(mounted) = mounted_trash() {
+        return Ok(mounted);
+    }
+    let home = match sudo::invoking_user() {
+        Some(user) => Some(user.home.clone()),
+        None => dirs_next::home_dir(),
+    };
+    match env::var("XDG_DATA_HOME") {
+        Ok(xdg) if !xdg.is_empty() => Ok(PathBuf::from(xdg).join("Trash")),
+        _ => Ok(home.context(NO_HOME)?.join(".local/share/Trash")),
+    }
+}
+
+fn setup_trash_dir(trash: &Path) -> io::Result<()> {
+    for dir in [trash.join("files"), trash.join("info")] {
+        sudo::create_dir_all(&dir)?;
+        let dir = CString::new(dir.as_os_str().as_bytes())?;
+        if unsafe { libc::access(dir.as_ptr(), libc::W_OK) } != 0 {
+            return Err(io::Error::last_os_error());
         }
-    } else {
-        dirs_next::home_dir().unwrap().join(".local/share/Trash")
+    }
+    Ok(())
+}
+
+fn setup_hint(e: &io::Error) -> &'static str {
+    match e.raw_os_error() {
+        Some(libc::EROFS) => "the filesystem is read-only",
+        Some(libc::ENOSPC) | Some(libc::EDQUOT) => "the disk or quota is full; free some space",
+        Some(libc::EACCES) | Some(libc::EPERM) => "check the ownership and permissions of the directory",
+        _ => "check that the location is usable",
+    }
+}
+
+static READ_ONLY_TRASH: OnceLock<String> = OnceLock::new();
+
+fn check_writable(trash: &Path, home_trash: &Path) -> Result<()> {
+    match READ_ONLY_TRASH.get() {
+        Some(failure) if trash == home_trash => Err(anyhow!("{failure}")),
+        _ => Ok(()),
+    }
+}
+
+fn resolve_trash_dir() -> Result<PathBuf, String> {
+    let trash = match &config::get().trash_dir {
+        Some(dir) => config::expand_path(dir),
+        None => default_trash_dir().map_err(|e| e.to_string())?,
+    };
+    let Err(e) = setup_trash_dir(&trash) else {
+        return Ok(trash);
     };
-    let _ = fs::create_dir_all(&trash);
-    let _ = fs::create_dir_all(trash.join("files"));
-    let _ = fs::create_dir_all(trash.join("info"));
+    let mut failure = format!("Cannot set up the trash in {}: {e} ({})", trash.display(), setup_hint(&e));
+    for fallback in config::get().fallback_trash_dirs.iter().map(|dir| config::expand_path(dir)) {
+        match setup_trash_dir(&fallback) {
+            Ok(()) => {
+                warn!("{failure}; using the fallback trash {}", fallback.display());
+                return Ok(fallback);
+            }
+            Err(e) => failure.push_str(&format!("; fallback {}: {e}", fallback.display())),
+        }
+    }
+    if config::get().fallback_trash_dirs.is_empty() {
+        failure.push_str("; fallback_trash_dirs in the config can name other locations to try");
+    }
+    if trash.join("info").is_dir() {
+        debug!("{failure}; using it read-only");
+        let _ = READ_ONLY_TRASH.set(failure);
+        return Ok(trash);
+    }
+    Err(failure)
+}
+
+pub fn find_trash_dir() -> Result<PathBuf> {
+    static TRASH: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+    let trash = TRASH.get_or_init(resolve_trash_dir).clone().map_err(|e| anyhow!(e))?;
+    debug!("trash directory: {}", trash.display());
     Ok(trash)
 }
 
-fn generate_unique_name(original: &std::path::Path) -> String {
+fn resolved(path: &Path) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize().map(|parent| parent.join(name)),
+        _ => path.canonicalize(),
+    }
+    .unwrap_or_else(|_| path.to_path_buf())
+}
+
+pub fn refuse_trash_itself(original: &Path, trashes: &[&Path]) -> Result<()> {
+    let original = resolved(original);
+    for trash in trashes {
+        let trash = resolved(trash);
+        if trash == original {
+            return Err(anyhow!("refusing to trash the trash directory itself (use --empty to clear it)"));
+        }
+        if trash.starts_with(&original) {
+            return Err(anyhow!("refusing to trash a directory containing the trash {}", trash.display()));
+        }
+    }
+    Ok(())
+}
+
+pub fn contains_current_dir(path: &Path) -> bool {
+    env::current_dir().is_ok_and(|cwd| cwd.starts_with(resolved(path)))
+}
+
+pub fn generate_unique_name(original: &std::path::Path) -> String {
     let stem = original.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
     let ext = original.extension().and_then(|s| s.to_str()).unwrap_or("");
     let id = nanoid!(10);
@@ -134,183 +255,1671 @@ fn generate_unique_name(original: &std::path::Path) -> String {
     }
 }
 
-pub fn move_to_trash(path_str: &str) -> Result<()> {
-    let _ = cleanup_old_trash();
-    let original_path = std::path::Path::new(path_str);
+const INFO_BATCH_SIZE: usize = 256;
+const SELINUX_XATTR: &str = "security.selinux";
+const CAPABILITY_XATTR: &str = "security.capability";
+const NO_HOME: &str = "Cannot determine the home directory; set HOME, or choose a trash with RIP_TRASH_DIR or --trash-dir";
+pub const BULK_INFO_BATCH_SIZE: usize = 8192;
+
+pub struct InfoBatch {
+    pending: Vec<(PathBuf, String, Option<u64>)>,
+    limit: usize,
+    fields: String,
+}
+
+impl Default for InfoBatch {
+    fn default() -> Self {
+        Self::with_limit(INFO_BATCH_SIZE)
+    }
+}
+
+impl InfoBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self { pending: Vec::new(), limit, fields: String::new() }
+    }
+
+    pub fn annotate(&mut self, key: &str, value: &str) {
+        self.fields.push_str(&format!("{key}={value}\n"));
+    }
+
+    fn push(&mut self, info_file: PathBuf, content: String, dir_size: Option<u64>) -> Result<()> {
+        self.pending.push((info_file, content + self.fields.as_str(), dir_size));
+        if self.pending.len() >= self.limit {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        let mut dirs = Vec::new();
+        let mut failed = 0;
+        for (info_file, content, dir_size) in self.pending.drain(..) {
+            if let Err(e) = fs::write(&info_file, content) {
+                warn!("Failed to write {}: {e}; the next rip run finishes it from the journal", info_file.display());
+                failed += 1;
+                continue;
+            }
+            sudo::hand_over(&info_file);
+            journal::complete(&info_file);
+            if let (Some(size), Some(trash), Some(name)) = (
+                dir_size,
+                info_file.parent().and_then(Path::parent),
+                info_file.file_stem().and_then(|s| s.to_str()),
+            ) {
+                if let Err(e) = record_directory_size(trash, name, size, &info_file) {
+                    warn!("{}: cannot record the directory size: {e}", info_file.display());
+                }
+            }
+            if let Some(dir) = info_file.parent() {
+                if !dirs.iter().any(|d: &PathBuf| d == dir) {
+                    dirs.push(dir.to_path_buf());
+                }
+            }
+        }
+        for dir in dirs {
+            fs::File::open(&dir)?.sync_all()?;
+        }
+        match failed {
+            0 => Ok(()),
+            n => Err(anyhow!("{n} trash records could not be written")),
+        }
+    }
+}
+
+impl Drop for InfoBatch {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+pub fn clean_if_due() {
+    if !crate::daemon::daemon_running() && schedule::cleanup_due() {
+        let _ = cleanup_old_trash();
+    }
+}
+
+pub fn move_to_trash(path_str: &str, batch: &mut InfoBatch) -> Result<()> {
+    let resolved_path = resolved(Path::new(path_str));
+    let original_path = match Path::new(path_str).file_name() {
+        Some(_) => Path::new(path_str),
+        None => resolved_path.as_path(),
+    };
     let metadata = original_path
         .symlink_metadata()
         .with_context(|| format!("No such file or directory: {path_str}"))?;
-    let original_absolute = if original_path.is_absolute() {
-        original_path.to_path_buf()
-    } else {
-        env::current_dir()?.join(original_path)
-    };
+    let size = path_size(original_path).ok();
+    let original_absolute = std::path::absolute(original_path)?;
 
-    let trash = find_trash_dir()?;
+    let home_trash = find_trash_dir()?;
+    let volume_trash = match config::get().trash_dir {
+        None => volume_trash_for(original_path, &home_trash),
+        Some(_) => None,
+    };
+    if let (None, None, Some(fstype)) = (
+        &volume_trash,
+        &config::get().trash_dir,
+        remote_filesystem(original_path, &home_trash),
+    ) {
+        match network_fs_policy() {
+            NetworkFs::Volume => {
+                return Err(anyhow!(
+                    "on a {fstype} network filesystem without a usable trash (set network_fs = \"copy\" or \"delete\")"
+                ));
+            }
+            NetworkFs::Copy => warn!("{path_str}: copying {} from a {fstype} network filesystem into the local trash", size.map(human_size).unwrap_or_else(|| "-".to_owned())),
+            NetworkFs::Delete => {
+                if !confirm(&format!("{path_str} is on a {fstype} network filesystem. Delete permanently? [y/N] ")) {
+                    return Err(anyhow!("left in place (network filesystem)"));
+                }
+                remove_recursively(original_path)?;
+                audit::record("purge", &original_absolute, size);
+                println!("{}", tr!("deleted-permanently", path = path_str));
+                return Ok(());
+            }
+        }
+    }
+    let recorded_path = volume_trash
+        .as_deref()
+        .and_then(|trash| path_within_volume(original_path, trash))
+        .unwrap_or_else(|| original_absolute.clone());
+    match &volume_trash {
+        Some(dir) => debug!("{path_str}: using volume trash {}", dir.display()),
+        None => debug!("{path_str}: using home trash {}", home_trash.display()),
+    }
+    let trash = volume_trash.unwrap_or(home_trash.clone());
+    check_writable(&trash, &home_trash)?;
+    refuse_trash_itself(&original_absolute, &[&trash, &home_trash])?;
+    if ephemeral_policy() == Ephemeral::Delete {
+        if let Some(reason) = volatile_reason(&trash) {
+            remove_recursively(original_path)?;
+            audit::record("purge", &original_absolute, size);
+            println!("{}", tr!("deleted-volatile-trash", path = path_str, reason = reason));
+            return Ok(());
+        }
+    }
     let files_dir = trash.join("files");
     let info_dir = trash.join("info");
     let trashed_name = generate_unique_name(original_path);
     let dest_file = files_dir.join(&trashed_name);
     let info_file = info_dir.join(format!("{trashed_name}.trashinfo"));
-    let deletion_date = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let now = sandbox::now();
+    let deletion_date = format_deletion_date(now);
     let encoded_path = encode(
-        original_absolute
+        recorded_path
             .to_str()
             .context("non-UTF8 path")?
     ).to_string();
 
-    let moved = if metadata.file_type().is_symlink() {
-        let _ = fs::remove_file(&dest_file);
-        if let Ok(target) = fs::read_link(original_path) {
-            std::os::unix::fs::symlink(target, &dest_file).is_ok()
-        } else {
-            let _ = std::os::unix::fs::symlink("/RIP_BROKEN_LINK", &dest_file);
-            true
+    let selinux_context = xattr::get(original_path, SELINUX_XATTR)
+        .ok()
+        .flatten()
+        .map(|raw| String::from_utf8_lossy(&raw).trim_end_matches('\0').to_owned());
+    let capability = xattr::get(original_path, CAPABILITY_XATTR).ok().flatten();
+    let encrypted = encryption_enabled() && !metadata.file_type().is_symlink();
+    let mut info = format!(
+        "[Trash Info]\nPath={encoded_path}\nDeletionDate={deletion_date}\nX-Rip-Sequence={}\n",
+        next_sequence(now)
+    );
+    if let Some(size) = size {
+        info.push_str(&format!("X-Rip-Size={size}\n"));
+    }
+    info.push_str(&format!(
+        "X-Rip-Type={}\nX-Rip-Mode={:o}\nX-Rip-Uid={}\nX-Rip-Gid={}\nX-Rip-Inode={}:{}\nX-Rip-TrashedBy={}\n",
+        file_kind(&metadata),
+        metadata.mode() & 0o7777,
+        metadata.uid(),
+        metadata.gid(),
+        metadata.dev(),
+        metadata.ino(),
+        sudo::trash_uid()
+    ));
+    if let Some(context) = selinux_context {
+        info.push_str(&format!("X-Rip-SELinux={context}\n"));
+    }
+    if let Some(capability) = capability {
+        info.push_str(&format!("X-Rip-Capability={}\n", hex::encode(capability)));
+    }
+    if encrypted {
+        info.push_str(&format!("X-Rip-Encrypted={ENCRYPTED_MARKER}\n"));
+    }
+
+    if metadata.is_file() {
+        if let Some((n, item)) = trashed_copy_of(original_path, &metadata) {
+            warn!("{path_str}: this content is already in the trash as item #{n} ({})", item.original_path.display());
+        }
+    }
+
+    journal::begin(&trash, &trashed_name, &original_absolute, &metadata, &(info.clone() + batch.fields.as_str()))?;
+    if let Err(e) = move_into_trash(path_str, original_path, &metadata, &dest_file, encrypted, &trash, &trashed_name) {
+        journal::settle(&trash, &trashed_name);
+        return Err(e);
+    }
+
+    if dedup_enabled() && !encrypted && metadata.is_file() && metadata.len() > 0 {
+        let digest = file_digest(&dest_file)?;
+        let _ = link_duplicate(&dest_file, &digest);
+        info.push_str(&format!("X-Rip-Sha256={digest}\n"));
+    }
+    batch.push(info_file, info, size.filter(|_| metadata.is_dir() && !encrypted))?;
+    audit::record("trash", &original_absolute, size);
+    Ok(())
+}
+
+pub fn snapshot(path: &Path, tags: &[String]) -> Result<()> {
+    let metadata = path.symlink_metadata().with_context(|| format!("No such file or directory: {}", path.display()))?;
+    let original = resolved(&std::path::absolute(path)?);
+    let home_trash = find_trash_dir()?;
+    let trash = match config::get().trash_dir {
+        None => volume_trash_for(&original, &home_trash).unwrap_or_else(|| home_trash.clone()),
+        Some(_) => home_trash.clone(),
+    };
+    check_writable(&trash, &home_trash)?;
+    refuse_trash_itself(&original, &[&trash, &home_trash])?;
+    let recorded_path = path_within_volume(&original, &trash).unwrap_or_else(|| original.clone());
+    let size = path_size(&original).ok();
+    let trashed_name = generate_unique_name(&original);
+    let dest_file = trash.join("files").join(&trashed_name);
+    let info_file = trash.join("info").join(format!("{trashed_name}.trashinfo"));
+    let now = sandbox::now();
+    let tags: Vec<&str> = std::iter::once("snapshot").chain(tags.iter().map(String::as_str).filter(|t| *t != "snapshot")).collect();
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\nX-Rip-Sequence={}\n{}X-Rip-Type={}\nX-Rip-Mode={:o}\nX-Rip-Uid={}\nX-Rip-Gid={}\nX-Rip-TrashedBy={}\nX-Rip-Tags={}\n",
+        encode(recorded_path.to_str().context("non-UTF8 path")?),
+        format_deletion_date(now),
+        next_sequence(now),
+        size.map(|size| format!("X-Rip-Size={size}\n")).unwrap_or_default(),
+        file_kind(&metadata),
+        metadata.mode() & 0o7777,
+        metadata.uid(),
+        metadata.gid(),
+        sudo::trash_uid(),
+        tags.join(",")
+    );
+
+    journal::begin(&trash, &trashed_name, &original, &metadata, &info)?;
+    if let Err(e) = copy_verified(&original, &dest_file) {
+        journal::settle(&trash, &trashed_name);
+        return Err(e);
+    }
+    let mut batch = InfoBatch::new();
+    batch.push(info_file, info, size.filter(|_| metadata.is_dir()))?;
+    batch.flush()?;
+    audit::record("snapshot", &original, size);
+    println!("{}", tr!("snapshot-saved", path = original.display().to_string(), size = size.map(human_size).unwrap_or_else(|| "-".to_owned())));
+    Ok(())
+}
+
+fn same_content(a: &Path, b: &Path) -> bool {
+    let (Ok(a), Ok(b)) = (fs::File::open(a), fs::File::open(b)) else { return false };
+    let (mut a, mut b) = (io::BufReader::new(a), io::BufReader::new(b));
+    loop {
+        let (Ok(x), Ok(y)) = (a.fill_buf(), b.fill_buf()) else { return false };
+        if x.is_empty() || y.is_empty() {
+            return x.is_empty() && y.is_empty();
+        }
+        let n = x.len().min(y.len());
+        if x[..n] != y[..n] {
+            return false;
+        }
+        a.consume(n);
+        b.consume(n);
+    }
+}
+
+fn trashed_copy_of(path: &Path, metadata: &fs::Metadata) -> Option<(usize, TrashItem)> {
+    static ITEMS: OnceLock<Vec<TrashItem>> = OnceLock::new();
+    let items = ITEMS.get_or_init(|| load_trash_items().unwrap_or_default());
+    let key = (metadata.dev(), metadata.ino());
+    items.iter().zip(1..).find_map(|(item, n)| {
+        let same_size = item.size == Some(metadata.len());
+        let same = (item.inode == Some(key) && same_size)
+            || item.trashed_path().symlink_metadata().is_ok_and(|m| (m.dev(), m.ino()) == key)
+            || (same_size && metadata.len() > 0 && item.compressed.is_none() && item.encrypted.is_none() && same_content(path, &item.trashed_path()));
+        same.then(|| (n, item.clone()))
+    })
+}
+
+fn move_into_trash(
+    path_str: &str,
+    original_path: &Path,
+    metadata: &fs::Metadata,
+    dest_file: &Path,
+    encrypted: bool,
+    trash: &Path,
+    trashed_name: &str,
+) -> Result<()> {
+    let renamed = match encrypted {
+        true => Err(io::Error::from_raw_os_error(libc::EXDEV)),
+        false => rename_inspected(original_path, metadata, dest_file),
+    };
+    match renamed {
+        Ok(()) => {
+            debug!("{path_str}: renamed into trash");
+            return journal::mark_moved(trash, trashed_name);
+        }
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {}
+        Err(e) if e.raw_os_error().is_none() => return Err(e.into()),
+        Err(e) => debug!("{path_str}: rename failed ({e}), copying into trash"),
+    }
+    if metadata.file_type().is_symlink() {
+        debug!("{path_str}: recreating symlink in trash");
+        let _ = fs::remove_file(dest_file);
+        match fs::read_link(original_path) {
+            Ok(target) => std::os::unix::fs::symlink(target, dest_file)?,
+            Err(_) => std::os::unix::fs::symlink("/RIP_BROKEN_LINK", dest_file)?,
         }
+        journal::mark_moved(trash, trashed_name)?;
+        fs::remove_file(original_path)?;
+    } else if encrypted {
+        debug!("{path_str}: encrypting into trash");
+        encrypt_into(original_path, dest_file)?;
+        journal::mark_moved(trash, trashed_name)?;
+        remove_recursively(original_path)?;
     } else if metadata.is_dir() {
-        copy_recursively(original_path, &dest_file)?;
+        debug!("{path_str}: copying directory into trash");
+        copy_recursively(original_path, dest_file)?;
+        journal::mark_moved(trash, trashed_name)?;
         remove_recursively(original_path)?;
-        true
-    } else if fs::rename(original_path, &dest_file).is_ok() {
-        true
     } else {
-        fs::copy(original_path, &dest_file)?;
+        debug!("{path_str}: copying into trash");
+        fs::copy(original_path, dest_file)?;
+        preserve_metadata(original_path, dest_file);
+        journal::mark_moved(trash, trashed_name)?;
         fs::remove_file(original_path)?;
-        true
-    };
+    }
+    Ok(())
+}
 
-    if moved {
-        fs::write(&info_file, format!("[Trash Info]\nPath={encoded_path}\nDeletionDate={deletion_date}\n"))?;
-        Ok(())
-    } else {
-        Err(anyhow!("Failed to move '{path_str}' to trash"))
+pub struct TrashItems {
+    trashes: Vec<PathBuf>,
+    entries: Option<fs::ReadDir>,
+    files_dir: PathBuf,
+    dir_sizes: Option<HashMap<String, u64>>,
+    file_names: Option<HashSet<OsString>>,
+}
+
+impl Iterator for TrashItems {
+    type Item = TrashItem;
+
+    fn next(&mut self) -> Option<TrashItem> {
+        loop {
+            if let Some(entries) = self.entries.as_mut() {
+                for entry in entries.flatten() {
+                    let info_path = entry.path();
+                    if info_path.extension().and_then(|s| s.to_str()) != Some("trashinfo") {
+                        continue;
+                    }
+                    let trash = self.files_dir.parent().unwrap_or(&self.files_dir);
+                    let dir_sizes = self.dir_sizes.get_or_insert_with(|| read_directory_sizes(trash));
+                    let file_names = self.file_names.get_or_insert_with(|| entry_names(&self.files_dir));
+                    if let Some(item) = read_trash_item(info_path, &self.files_dir, dir_sizes, file_names) {
+                        return Some(item);
+                    }
+                }
+            }
+            let trash = self.trashes.pop()?;
+            self.entries = fs::read_dir(trash.join("info")).ok();
+            self.files_dir = trash.join("files");
+            self.dir_sizes = None;
+            self.file_names = None;
+        }
     }
 }
 
-fn load_trash_items() -> Result<Vec<TrashItem>> {
+pub fn trash_roots() -> Result<Vec<PathBuf>> {
     let trash = find_trash_dir()?;
-    let info_dir = trash.join("info");
-    let files_dir = trash.join("files");
-    let mut items = Vec::new();
+    let mut roots = match config::get().trash_dir {
+        None => volume_trashes(&trash),
+        Some(_) => Vec::new(),
+    };
+    roots.insert(0, trash);
+    Ok(roots)
+}
+
+pub fn iter_trash_items() -> Result<TrashItems> {
+    let mut roots = trash_roots()?;
+    roots.reverse();
+    Ok(TrashItems {
+        trashes: roots,
+        entries: None,
+        files_dir: PathBuf::new(),
+        dir_sizes: None,
+        file_names: None,
+    })
+}
 
-    let Ok(entries) = fs::read_dir(&info_dir) else {
-        return Ok(items);
+pub fn iter_trash_items_in(trash: &Path) -> TrashItems {
+    TrashItems {
+        trashes: vec![trash.to_path_buf()],
+        entries: None,
+        files_dir: PathBuf::new(),
+        dir_sizes: None,
+        file_names: None,
+    }
+}
+
+pub fn read_directory_sizes(trash: &Path) -> HashMap<String, u64> {
+    let Ok(content) = fs::read_to_string(trash.join("directorysizes")) else {
+        return HashMap::new();
     };
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let size = fields.next()?.parse().ok()?;
+            let name = decode(fields.nth(1)?).ok()?.into_owned();
+            Some((name, size))
+        })
+        .collect()
+}
+
+fn write_directory_sizes(trash: &Path, lines: &[String]) -> Result<()> {
+    let path = trash.join("directorysizes");
+    let tmp = trash.join(format!(".directorysizes.{}", nanoid!(6)));
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &path)?;
+    sudo::hand_over(&path);
+    Ok(())
+}
+
+fn directory_size_lines(trash: &Path, except: &str) -> Vec<String> {
+    fs::read_to_string(trash.join("directorysizes"))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| {
+            line.splitn(3, ' ')
+                .nth(2)
+                .and_then(|name| decode(name).ok())
+                .is_some_and(|name| name != except)
+        })
+        .map(str::to_owned)
+        .collect()
+}
+
+pub fn record_directory_size(trash: &Path, name: &str, size: u64, info_file: &Path) -> Result<()> {
+    let mtime = info_file.metadata()?.mtime();
+    let mut lines = directory_size_lines(trash, name);
+    lines.push(format!("{size} {mtime} {}", encode(name)));
+    write_directory_sizes(trash, &lines)
+}
+
+pub fn forget_directory_size(trash: &Path, name: &str) -> Result<()> {
+    if !trash.join("directorysizes").exists() {
+        return Ok(());
+    }
+    write_directory_sizes(trash, &directory_size_lines(trash, name))
+}
+
+fn next_sequence(now: DateTime<Utc>) -> u64 {
+    static LAST: AtomicU64 = AtomicU64::new(0);
+    let now = now.timestamp_nanos_opt().unwrap_or_default() as u64;
+    let last = LAST.fetch_max(now, AtomicOrdering::SeqCst);
+    match last >= now {
+        true => LAST.fetch_add(1, AtomicOrdering::SeqCst) + 1,
+        false => now,
+    }
+}
+
+pub fn format_deletion_date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local).format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+pub fn parse_deletion_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(date).or_else(|_| DateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f%#z")) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    let naive = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| Local.from_local_datetime(&(naive + chrono::Duration::hours(1))).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+}
 
-    for entry in entries.flatten() {
-        let info_path = entry.path();
-        if info_path.extension().and_then(|s| s.to_str()) != Some("trashinfo") {
-            continue;
+pub fn entry_names(dir: &Path) -> HashSet<OsString> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.file_name()).collect())
+        .unwrap_or_default()
+}
+
+fn read_trash_item(
+    info_path: PathBuf,
+    files_dir: &Path,
+    dir_sizes: &HashMap<String, u64>,
+    file_names: &HashSet<OsString>,
+) -> Option<TrashItem> {
+    let content = match fs::read_to_string(&info_path) {
+        Ok(c) => c,
+        Err(e) => {
+            debug!("{}: {e}; skipping (rip --check --repair can fix it)", info_path.display());
+            return None;
         }
-        let content = match fs::read_to_string(&info_path) {
-            Ok(c) => c,
-            Err(_) => {
-                let _ = fs::remove_file(&info_path);
-                continue;
-            }
-        };
+    };
 
-        let mut path_val = None;
-        let mut date_val = None;
-        for line in content.lines() {
-            if let Some(v) = line.strip_prefix("Path=") {
-                path_val = Some(v.to_owned());
-            }
-            if let Some(v) = line.strip_prefix("DeletionDate=") {
-                date_val = Some(v.to_owned());
-            }
+    let mut path_val = None;
+    let mut date_val = None;
+    let mut size = None;
+    let mut compressed = None;
+    let mut encrypted = None;
+    let mut sha256 = None;
+    let mut tags = Vec::new();
+    let mut note = None;
+    let mut kind = None;
+    let mut mode = None;
+    let mut uid = None;
+    let mut gid = None;
+    let mut selinux = None;
+    let mut capability = None;
+    let mut sequence = None;
+    let mut inode = None;
+    let mut trashed_by = None;
+    let mut operation = None;
+    for line in content.lines() {
+        if let Some(v) = line.strip_prefix("Path=") {
+            path_val = Some(v.to_owned());
+        }
+        if let Some(v) = line.strip_prefix("DeletionDate=") {
+            date_val = Some(v.to_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Size=") {
+            size = v.trim().parse::<u64>().ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Compressed=") {
+            compressed = Some(v.trim().to_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Encrypted=") {
+            encrypted = Some(v.trim().to_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Sha256=") {
+            sha256 = Some(v.trim().to_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Tags=") {
+            tags = v.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_owned).collect();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Note=") {
+            note = decode(v.trim()).ok().map(|n| n.into_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Type=") {
+            kind = Some(v.trim().to_owned());
         }
+        if let Some(v) = line.strip_prefix("X-Rip-Mode=") {
+            mode = u32::from_str_radix(v.trim(), 8).ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Uid=") {
+            uid = v.trim().parse::<u32>().ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Gid=") {
+            gid = v.trim().parse::<u32>().ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-SELinux=") {
+            selinux = Some(v.trim().to_owned());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Capability=") {
+            capability = hex::decode(v.trim()).ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Sequence=") {
+            sequence = v.trim().parse::<u64>().ok();
+        }
+        if let Some((dev, ino)) = line.strip_prefix("X-Rip-Inode=").and_then(|v| v.trim().split_once(':')) {
+            inode = dev.parse::<u64>().ok().zip(ino.parse::<u64>().ok());
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-TrashedBy=") {
+            trashed_by = v.trim().parse::<u32>().ok();
+        }
+        if let Some(v) = line.strip_prefix("X-Rip-Operation=") {
+            operation = Some(v.trim().to_owned());
+        }
+    }
 
-        let Some(path_val) = path_val else { continue };
-        let Some(date_str) = date_val else { continue };
+    let original_path = PathBuf::from(OsString::from_vec(decode_binary(path_val?.trim().as_bytes()).into_owned()));
+    let original_path = match files_dir.parent().and_then(trash_topdir) {
+        Some(topdir) if original_path.is_relative() => topdir.join(original_path),
+        _ => original_path,
+    };
+    let deletion_date = match parse_deletion_date(&date_val?) {
+        Some(date) => date,
+        None => {
+            debug!("{}: unreadable DeletionDate; using the record's modification time", info_path.display());
+            DateTime::from(info_path.metadata().and_then(|m| m.modified()).ok()?)
+        }
+    };
 
-        let original_path = match decode(&path_val) {
-            Ok(p) => PathBuf::from(p.into_owned()),
-            Err(_) => continue,
-        };
+    let trashed_name = info_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown")
+        .to_owned();
 
-        let deletion_date = DateTime::parse_from_rfc3339(&date_str)
-            .or_else(|_| DateTime::parse_from_rfc3339(&format!("{date_str}Z")))
-            .map(|dt| dt.with_timezone(&Utc))
-            .unwrap_or_else(|_| Utc::now());
-
-        let trashed_name = info_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_owned();
-
-        if files_dir.join(&trashed_name).exists() {
-            items.push(TrashItem {
-                original_path,
-                deletion_date,
-                trashed_name,
-                info_path,
-            });
-        } else {
-            let _ = fs::remove_file(&info_path);
-        }
+    let Ok(metadata) = files_dir.join(&trashed_name).symlink_metadata() else {
+        debug!("{}: no trashed file; skipping (rip --check --repair can fix it)", info_path.display());
+        return None;
+    };
+    if !file_names.contains(OsStr::new(&trashed_name)) {
+        debug!("{}: files/{trashed_name} only matches a differently named entry; skipping (rip --check --repair can resolve it)", info_path.display());
+        return None;
     }
+    let size = size
+        .or_else(|| (!metadata.is_dir() && compressed.is_none() && encrypted.is_none()).then_some(metadata.len()))
+        .or_else(|| dir_sizes.get(&trashed_name).copied());
+    Some(TrashItem {
+        original_path,
+        deletion_date,
+        trashed_name,
+        info_path,
+        size,
+        compressed,
+        encrypted,
+        sha256,
+        tags,
+        note,
+        kind,
+        mode,
+        owner: uid.zip(gid),
+        selinux,
+        capability,
+        sequence,
+        inode,
+        trashed_by,
+        operation,
+    })
+}
+
+pub fn set_info_field(info_path: &Path, key: &str, value: &str) -> Result<()> {
+    let content = fs::read_to_string(info_path)?;
+    let prefix = format!("{key}=");
+    let mut lines: Vec<&str> = content.lines().filter(|l| !l.starts_with(&prefix)).collect();
+    let line = format!("{key}={value}");
+    lines.push(&line);
+    let tmp = info_path.with_extension("trashinfo.tmp");
+    fs::write(&tmp, lines.join("\n") + "\n")?;
+    fs::rename(&tmp, info_path)?;
+    Ok(())
+}
 
-    items.sort_by_key(|i| std::cmp::Reverse(i.deletion_date));
+pub fn load_trash_items() -> Result<Vec<TrashItem>> {
+    let mut items: Vec<_> = iter_trash_items()?.collect();
+    items.sort_by(|a, b| trash_order(b, a));
     Ok(items)
 }
 
-pub fn list_trash() -> Result<()> {
-    let items = load_trash_items()?;
-    if items.is_empty() {
-        println!("Trash is empty");
-        return Ok(());
+pub fn newest_items(n: usize) -> Result<Vec<TrashItem>> {
+    let mut heap = BinaryHeap::with_capacity(n + 1);
+    for item in iter_trash_items()? {
+        heap.push(Reverse(NewestFirst(item)));
+        if heap.len() > n {
+            heap.pop();
+        }
     }
-    println!(" # Date & Time                 Original Path");
-    println!("────────────────────────────────────────────────────────────────");
-    for (i, item) in items.iter().enumerate() {
-        println!(
-            "{:>3} {}  {}",
-            i + 1,
-            item.deletion_date
-                .with_timezone(&Local)
-                .format("%Y-%m-%d %H:%M:%S"),
-            item.original_path.display()
-        );
+    Ok(heap.into_sorted_vec().into_iter().map(|Reverse(NewestFirst(item))| item).collect())
+}
+
+struct NewestFirst(TrashItem);
+
+pub fn trash_order(a: &TrashItem, b: &TrashItem) -> Ordering {
+    a.deletion_date
+        .cmp(&b.deletion_date)
+        .then_with(|| a.sequence.cmp(&b.sequence))
+        .then_with(|| a.trashed_name.cmp(&b.trashed_name))
+}
+
+impl PartialEq for NewestFirst {
+    fn eq(&self, other: &Self) -> bool {
+        trash_order(&self.0, &other.0) == Ordering::Equal
     }
-    Ok(())
 }
 
-pub fn restore_nth(n: usize) -> Result<()> {
-    let items = load_trash_items()?;
-    let item = items.get(n - 1).context("No such item")?.clone();
-    let trash = find_trash_dir()?;
-    let src = trash.join("files").join(&item.trashed_name);
-    let mut target = item.original_path.clone();
+impl Eq for NewestFirst {}
 
-    if target.exists() {
-        let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("restored");
-        let ext = target.extension().and_then(|s| s.to_str()).unwrap_or("");
-        let date = Local::now().format("%Y-%m-%d");
-        let mut p = target.parent().unwrap_or(std::path::Path::new(".")).to_path_buf();
-        p.push(format!("{stem} (restored {date})"));
-        if !ext.is_empty() {
-            p.set_extension(ext);
-        }
-        target = p;
+impl PartialOrd for NewestFirst {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    fs::rename(&src, &target)?;
-    fs::remove_file(&item.info_path)?;
-    println!("Restored: {}", target.display());
-    Ok(())
+impl Ord for NewestFirst {
+    fn cmp(&self, other: &Self) -> Ordering {
+        trash_order(&self.0, &other.0)
+    }
 }
 
-pub fn empty_trash() -> Result<()> {
-    let trash = find_trash_dir()?;
-    for sub in ["files", "info"] {
-        let p = trash.join(sub);
-        if p.exists() {
-            fs::remove_dir_all(&p)?;
-            fs::create_dir(&p)?;
+pub fn list_trash(long: bool, filter: &ItemFilter, latest_only: bool, template: Option<&Template>) -> Result<()> {
+    let mut items: Vec<_> = load_trash_items()?
+        .into_iter()
+        .enumerate()
+        .filter(|(_, item)| filter.matches(item))
+        .collect();
+    let mut older: HashMap<PathBuf, usize> = HashMap::new();
+    if latest_only {
+        let mut seen = HashSet::new();
+        items.retain(|(_, item)| {
+            let newest = seen.insert(item.original_path.clone());
+            if !newest {
+                *older.entry(item.original_path.clone()).or_default() += 1;
+            }
+            newest
+        });
+    }
+    if let Some(template) = template {
+        for (i, item) in &items {
+            println!("{}", template.render(i + 1, item));
         }
+        return Ok(());
     }
-    println!("Trash emptied");
-    Ok(())
-}
\ No newline at end of file
+    if items.is_empty() && !filter.is_empty() {
+        println!("{}", tr!("no-items", filter = filter.to_string()));
+        return Ok(());
+    }
+    if items.is_empty() {
+        println!("{}", tr!("trash-empty"));
+        return Ok(());
+    }
+    if long {
+        println!(" # Date & Time              Size  Original Path");
+    } else {
+        println!(" # Date & Time                 Original Path");
+    }
+    println!("────────────────────────────────────────────────────────────────");
+    for (i, item) in &items {
+        let date = item.deletion_date
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S");
+        let mut tags = match item.tags.is_empty() {
+            true => String::new(),
+            false => format!("  [{}]", item.tags.join(", ")),
+        };
+        if let Some(count) = older.get(&item.original_path) {
+            tags.push_str(&format!("  (+{count} older)"));
+        }
+        if let Some(uid) = item.trashed_by.filter(|uid| *uid != sudo::trash_uid()) {
+            tags.push_str(&format!("  (by {})", sudo::user_name(uid)));
+        }
+        if long {
+            let size = item.size.map(human_size).unwrap_or_else(|| "-".to_owned());
+            let suffix = if item.kind.as_deref() == Some("dir") { "/" } else { "" };
+            println!("{:>3} {date}  {size:>7}  {}{suffix}{tags}", i + 1, item.original_path.display());
+            if let Some(note) = &item.note {
+                println!("{:>32}  note: {note}", "");
+            }
+        } else {
+            println!("{:>3} {date}  {}{tags}", i + 1, item.original_path.display());
+        }
+    }
+    if long {
+        let total: u64 = items.iter().filter_map(|(_, i)| i.size).sum();
+        println!("────────────────────────────────────────────────────────────────");
+        println!("{}", tr!("list-total", count = items.len(), size = human_size(total)));
+    }
+    Ok(())
+}
+
+pub fn list_grouped(tags: &[String]) -> Result<()> {
+    let items: Vec<_> = load_trash_items()?.into_iter().zip(1..).filter(|(item, _)| has_tags(item, tags)).collect();
+    if items.is_empty() {
+        println!("{}", tr!("trash-empty"));
+        return Ok(());
+    }
+    let mut groups: BTreeMap<&Path, Vec<(&TrashItem, usize)>> = BTreeMap::new();
+    for (item, n) in &items {
+        groups.entry(item.original_path.parent().unwrap_or(Path::new("/"))).or_default().push((item, *n));
+    }
+    for (dir, members) in &groups {
+        let total: u64 = members.iter().filter_map(|(item, _)| item.size).sum();
+        let plural = if members.len() == 1 { "" } else { "s" };
+        println!("{}/  ({} item{plural}, {})", dir.display().to_string().trim_end_matches('/'), members.len(), human_size(total));
+        for (item, n) in members {
+            let date = item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            let name = item.original_path.file_name().unwrap_or_default().to_string_lossy();
+            let suffix = if item.kind.as_deref() == Some("dir") { "/" } else { "" };
+            println!("  {n:>3} {date}  {name}{suffix}");
+        }
+    }
+    let total: u64 = items.iter().filter_map(|(item, _)| item.size).sum();
+    println!("────────────────────────────────────────────────────────────────");
+    println!("{} items in {} directories, {}", items.len(), groups.len(), human_size(total));
+    Ok(())
+}
+
+pub fn trash_locations() -> Result<Vec<(String, PathBuf)>> {
+    let current = find_trash_dir()?;
+    let default = default_trash_dir().unwrap_or_else(|_| current.clone());
+    let label = match config::active_trash() {
+        Some(name) => name,
+        None if current == default => "default",
+        None => "current",
+    };
+    let mut locations = vec![(label.to_owned(), current)];
+    let named = config::get().trashes.iter().map(|(name, t)| (name.clone(), config::expand_path(&t.path)));
+    let volumes = volume_trashes(&default).into_iter().map(|trash| {
+        let topdir = trash_topdir(&trash).unwrap_or(&trash).display().to_string();
+        (topdir, trash)
+    });
+    for (name, path) in std::iter::once(("default".to_owned(), default)).chain(named).chain(volumes) {
+        if !locations.iter().any(|(_, p)| *p == path) {
+            locations.push((name, path));
+        }
+    }
+    Ok(locations)
+}
+
+pub fn open_trash(path: Option<&Path>) -> Result<()> {
+    let home_trash = find_trash_dir()?;
+    let trash = match (path, &config::get().trash_dir) {
+        (Some(path), None) => {
+            let path = path.canonicalize().with_context(|| format!("No such file or directory: {}", path.display()))?;
+            volume_trash_for(&path, &home_trash).unwrap_or(home_trash)
+        }
+        _ => home_trash,
+    };
+    let files = trash.join("files");
+    debug!("opening {}", files.display());
+    let status = std::process::Command::new("xdg-open")
+        .arg(&files)
+        .status()
+        .context("Cannot run xdg-open")?;
+    if !status.success() {
+        return Err(anyhow!("xdg-open {} failed ({status})", files.display()));
+    }
+    Ok(())
+}
+
+pub fn list_all_trashes(long: bool) -> Result<()> {
+    let mut items = Vec::new();
+    for (name, path) in trash_locations()? {
+        items.extend(iter_trash_items_in(&path).map(|item| (name.clone(), item)));
+    }
+    if items.is_empty() {
+        println!("All trashes are empty");
+        return Ok(());
+    }
+    items.sort_by(|(_, a), (_, b)| trash_order(b, a));
+    let width = items.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(5);
+    if long {
+        println!("{:<width$}  Date & Time             Size  Original Path", "Trash");
+    } else {
+        println!("{:<width$}  Date & Time          Original Path", "Trash");
+    }
+    println!("────────────────────────────────────────────────────────────────");
+    for (name, item) in &items {
+        let date = item.deletion_date
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S");
+        if long {
+            let size = item.size.map(human_size).unwrap_or_else(|| "-".to_owned());
+            println!("{name:<width$}  {date}  {size:>7}  {}", item.original_path.display());
+        } else {
+            println!("{name:<width$}  {date}  {}", item.original_path.display());
+        }
+    }
+    if long {
+        let total: u64 = items.iter().filter_map(|(_, i)| i.size).sum();
+        println!("────────────────────────────────────────────────────────────────");
+        println!("{} items, {}", items.len(), human_size(total));
+    }
+    Ok(())
+}
+
+pub fn print_stats() -> Result<()> {
+    let mut rows = Vec::new();
+    let mut oldest: Option<TrashItem> = None;
+    let mut largest: Option<TrashItem> = None;
+    for (name, path) in trash_locations()? {
+        let (mut count, mut total, mut unknown) = (0, 0, 0);
+        for item in iter_trash_items_in(&path) {
+            count += 1;
+            total += item.size.unwrap_or(0);
+            unknown += usize::from(item.size.is_none());
+            if oldest.as_ref().is_none_or(|o| trash_order(&item, o) == Ordering::Less) {
+                oldest = Some(item.clone());
+            }
+            if item.size > largest.as_ref().and_then(|l| l.size) {
+                largest = Some(item);
+            }
+        }
+        rows.push((name, path, count, total, unknown));
+    }
+    let width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0).max(5);
+    println!("{:<width$}  Items     Size     Free  Location", "Trash");
+    println!("────────────────────────────────────────────────────────────────");
+    for (name, path, count, total, unknown) in &rows {
+        let free = disk_usage(path).map(|usage| human_size(usage.available)).unwrap_or_else(|_| "-".to_owned());
+        let note = if *unknown > 0 { format!("  ({unknown} without a recorded size)") } else { String::new() };
+        println!("{name:<width$}  {count:>5}  {:>7}  {free:>7}  {}{note}", human_size(*total), path.display());
+    }
+    println!("────────────────────────────────────────────────────────────────");
+    let count: usize = rows.iter().map(|(_, _, count, ..)| count).sum();
+    let total: u64 = rows.iter().map(|(_, _, _, total, _)| total).sum();
+    println!("{:<width$}  {count:>5}  {:>7}", "Total", human_size(total));
+    if let Some(item) = oldest {
+        println!("Oldest:  {}  {}", item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"), item.original_path.display());
+    }
+    if let Some(item) = largest {
+        println!("Largest: {}  {}", human_size(item.size.unwrap_or(0)), item.original_path.display());
+    }
+    Ok(())
+}
+
+pub fn parse_tag(tag: &str) -> Result<String> {
+    if tag.is_empty() || tag.contains(|c: char| c == ',' || c.is_whitespace() || c.is_control()) {
+        return Err(anyhow!("Invalid tag {tag:?}: use a single word without commas"));
+    }
+    Ok(tag.to_owned())
+}
+
+pub fn has_tags(item: &TrashItem, tags: &[String]) -> bool {
+    tags.iter().all(|tag| item.tags.contains(tag))
+}
+
+pub fn tag_nth(n: usize, tag: &str) -> Result<()> {
+    let tag = parse_tag(tag)?;
+    let item = nth_item(n)?;
+    let mut tags = item.tags.clone();
+    if !tags.contains(&tag) {
+        tags.push(tag);
+    }
+    set_info_field(&item.info_path, "X-Rip-Tags", &tags.join(","))?;
+    println!("{}", tr!("tagged", path = item.original_path.display().to_string(), tags = tags.join(", ")));
+    Ok(())
+}
+
+pub fn empty_tagged(tags: &[String], shred: bool) -> Result<()> {
+    let (items, others): (Vec<_>, Vec<_>) = iter_trash_items()?.filter(|item| has_tags(item, tags)).partition(|item| check_owner(item).is_ok());
+    let doomed: Vec<PathBuf> = items.iter().map(TrashItem::trashed_path).collect();
+    hooks::run("pre_empty", &doomed)?;
+    if shred && !items.is_empty() {
+        eprintln!("{SHRED_CAVEAT}");
+    }
+    let (mut count, mut freed) = (0, 0);
+    let mut failed = Vec::new();
+    for item in &items {
+        let removed = match shred {
+            true => shred_path(&item.trashed_path()).and_then(|()| remove_trashed(item)),
+            false => remove_trashed(item),
+        };
+        match removed.and_then(|is_dir| match is_dir {
+            true => forget_directory_size(item.trash_dir(), &item.trashed_name),
+            false => Ok(()),
+        }) {
+            Ok(()) => {
+                count += 1;
+                freed += item.size.unwrap_or(0);
+                audit::record("empty", &item.original_path, item.size);
+            }
+            Err(e) => {
+                warn!("{}: {e}", item.original_path.display());
+                failed.push(item);
+            }
+        }
+    }
+    hooks::run("post_empty", &doomed)?;
+    if !others.is_empty() {
+        println!("{}", tr!("kept-other-users", count = others.len()));
+    }
+    if failed.is_empty() {
+        println!("{}", tr!("deleted-tagged", count = count, tags = tags.join(", ")));
+        return Ok(());
+    }
+    println!("{}", tr!("empty-partial", count = count, size = human_size(freed)));
+    for item in &failed {
+        println!("  {}", item.original_path.display());
+    }
+    Err(anyhow!("{} items could not be removed", failed.len()))
+}
+
+pub fn nth_item(n: usize) -> Result<TrashItem> {
+    let mut newest = newest_items(n)?;
+    if n == 0 || newest.len() < n {
+        return Err(anyhow!("No such item"));
+    }
+    newest.pop().context("No such item")
+}
+
+pub fn purge_nth(n: usize, shred: bool) -> Result<()> {
+    let item = nth_item(n)?;
+    if shred {
+        eprintln!("{SHRED_CAVEAT}");
+        shred_item(&item)?;
+    } else {
+        purge_item(&item)?;
+    }
+    match item.size {
+        Some(size) => println!("{}", tr!("deleted-item", path = item.original_path.display().to_string(), size = human_size(size))),
+        None => println!("{}", tr!("deleted-permanently", path = item.original_path.display().to_string())),
+    }
+    Ok(())
+}
+
+pub fn purge_matching(filter: &ItemFilter, shred: bool) -> Result<()> {
+    let mut items: Vec<_> = iter_trash_items()?.filter(|item| filter.matches(item)).collect();
+    if items.is_empty() {
+        println!("{}", tr!("no-items", filter = filter.to_string()));
+        return Ok(());
+    }
+    items.sort_by(trash_order);
+    let total: u64 = items.iter().filter_map(|item| item.size).sum();
+    for item in &items {
+        let size = item.size.map(human_size).unwrap_or_else(|| "-".to_owned());
+        println!("  {size:>7}  {}", item.original_path.display());
+    }
+    if !confirm(&tr!("confirm-purge", count = items.len(), size = human_size(total))) {
+        println!("{}", tr!("nothing-deleted"));
+        return Ok(());
+    }
+    if shred {
+        eprintln!("{SHRED_CAVEAT}");
+    }
+    let (mut count, mut freed) = (0, 0);
+    for item in &items {
+        let removed = match shred {
+            true => shred_item(item),
+            false => purge_item(item),
+        };
+        match removed {
+            Ok(()) => {
+                count += 1;
+                freed += item.size.unwrap_or(0);
+            }
+            Err(e) => warn!("{}: {e}", item.original_path.display()),
+        }
+    }
+    println!("{}", tr!("deleted-matching", count = count, filter = filter.to_string(), size = human_size(freed)));
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreOutput {
+    Human,
+    Porcelain { nul: bool },
+}
+
+pub fn print_restored(target: &Path, output: RestoreOutput) -> Result<()> {
+    match output {
+        RestoreOutput::Human => println!("{}", tr!("restored", path = target.display().to_string())),
+        RestoreOutput::Porcelain { nul } => {
+            let mut out = io::stdout().lock();
+            out.write_all(target.as_os_str().as_bytes())?;
+            out.write_all(if nul { b"\0" } else { b"\n" })?;
+        }
+    }
+    Ok(())
+}
+
+pub fn versions_of(path: &Path) -> Result<(PathBuf, Vec<TrashItem>)> {
+    let original = resolved(&std::path::absolute(path)?);
+    let mut versions: Vec<_> = iter_trash_items()?.filter(|item| item.original_path == original).collect();
+    versions.sort_by(trash_order);
+    Ok((original, versions))
+}
+
+pub fn list_versions(path: &Path) -> Result<()> {
+    let (original, versions) = versions_of(path)?;
+    if versions.is_empty() {
+        println!("No trashed versions of {}", original.display());
+        return Ok(());
+    }
+    println!("Versions of {}:", original.display());
+    println!("  V Date & Time              Size");
+    println!("────────────────────────────────────────────────────────────────");
+    for (item, v) in versions.iter().zip(1..) {
+        let date = item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        let size = item.size.map(human_size).unwrap_or_else(|| "-".to_owned());
+        let latest = if v == versions.len() { "  (latest)" } else { "" };
+        println!("{v:>3} {date}  {size:>7}{latest}");
+    }
+    Ok(())
+}
+
+pub fn restore_version(path: &Path, version: Option<usize>, output: RestoreOutput) -> Result<()> {
+    let (original, mut versions) = versions_of(path)?;
+    let count = versions.len();
+    let item = match version {
+        _ if count == 0 => return Err(anyhow!("No trashed versions of {}", original.display())),
+        None => versions.pop(),
+        Some(v) => (v > 0).then(|| versions.into_iter().nth(v - 1)).flatten(),
+    }
+    .with_context(|| format!("{} has {count} trashed versions (see rip --versions)", original.display()))?;
+    let target = restore_item(&item)?;
+    print_restored(&target, output)
+}
+
+pub fn restore_matching(since: Option<Span>, from: Option<&Path>, output: RestoreOutput) -> Result<()> {
+    let cutoff = since.map(|span| span.before(sandbox::now()));
+    let from = from.map(|dir| std::path::absolute(dir).map(|dir| resolved(&dir))).transpose()?;
+    let mut items: Vec<_> = iter_trash_items()?
+        .filter(|item| cutoff.is_none_or(|cutoff| item.deletion_date >= cutoff))
+        .filter(|item| from.as_ref().is_none_or(|dir| item.original_path.starts_with(dir)))
+        .collect();
+    let mut what = Vec::new();
+    if let Some(dir) = &from {
+        what.push(format!("from {}", dir.display()));
+    }
+    if let Some(span) = since {
+        what.push(format!("in the last {span}"));
+    }
+    let what = what.join(" ");
+    if items.is_empty() {
+        println!("{}", tr!("nothing-trashed-window", what = what));
+        return Ok(());
+    }
+    items.sort_by(|a, b| a.original_path.components().count().cmp(&b.original_path.components().count()).then_with(|| trash_order(a, b)));
+    for item in &items {
+        let date = item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        println!("  {date}  {}", item.original_path.display());
+    }
+    if !confirm(&tr!("confirm-restore", count = items.len(), what = what.as_str())) {
+        println!("{}", tr!("nothing-restored"));
+        return Ok(());
+    }
+    restore_each(&items, output, from.is_some())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn near_matches(original: &Path) -> Result<Vec<(usize, TrashItem)>> {
+    let wanted = original.to_string_lossy();
+    let name = original.file_name();
+    let mut matches: Vec<_> = load_trash_items()?
+        .into_iter()
+        .zip(1..)
+        .map(|(item, n)| {
+            let distance = edit_distance(&wanted, &item.original_path.to_string_lossy());
+            let same_name = name.is_some() && item.original_path.file_name() == name;
+            ((!same_name, distance), n, item)
+        })
+        .filter(|((different_name, distance), _, _)| !different_name || *distance <= wanted.len() / 3 + 2)
+        .collect();
+    matches.sort_by_key(|(rank, n, _)| (*rank, *n));
+    Ok(matches.into_iter().take(5).map(|(_, n, item)| (n, item)).collect())
+}
+
+pub fn restore_path(path: &Path, output: RestoreOutput) -> Result<()> {
+    let (original, mut versions) = versions_of(path)?;
+    if let Some(item) = versions.pop() {
+        let target = restore_item(&item)?;
+        return print_restored(&target, output);
+    }
+    let suggestions = near_matches(&original)?;
+    if suggestions.is_empty() {
+        return Err(anyhow!("Nothing trashed from {}", original.display()));
+    }
+    let lines: Vec<String> = suggestions
+        .iter()
+        .map(|(n, item)| {
+            let date = item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            format!("  rip --restore {n:<4} # {} ({date})", item.original_path.display())
+        })
+        .collect();
+    Err(anyhow!("Nothing trashed from {}; did you mean:\n{}", original.display(), lines.join("\n")))
+}
+
+pub fn restore_nth(n: usize, output: RestoreOutput) -> Result<()> {
+    let target = restore_item(&nth_item(n)?)?;
+    print_restored(&target, output)
+}
+
+pub fn restore_each(items: &[TrashItem], output: RestoreOutput, recreate_parents: bool) -> Result<()> {
+    let mut failed = 0;
+    for item in items {
+        let parent = item.original_path.parent().filter(|_| recreate_parents);
+        let recreated = parent.map_or(Ok(()), |parent| {
+            fs::create_dir_all(parent).with_context(|| format!("Cannot recreate {}", parent.display()))
+        });
+        match recreated.and_then(|()| restore_item(item)) {
+            Ok(target) => print_restored(&target, output)?,
+            Err(e) => {
+                eprintln!("rip: {}: {e}", item.original_path.display());
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(anyhow!("{failed} of {} items could not be restored", items.len())),
+    }
+}
+
+pub fn restore_last(n: usize, output: RestoreOutput) -> Result<()> {
+    if n == 0 {
+        return Err(anyhow!("--restore-last needs at least 1 item"));
+    }
+    let mut items = newest_items(n)?;
+    if items.is_empty() {
+        return Err(anyhow!("Trash is empty"));
+    }
+    items.reverse();
+    restore_each(&items, output, false)
+}
+
+fn file_kind(metadata: &fs::Metadata) -> &'static str {
+    let kind = metadata.file_type();
+    if kind.is_symlink() {
+        "symlink"
+    } else if kind.is_dir() {
+        "dir"
+    } else if kind.is_file() {
+        "file"
+    } else {
+        "other"
+    }
+}
+
+fn restore_attributes(item: &TrashItem, target: &Path) {
+    if item.kind.as_deref() == Some("symlink") {
+        return;
+    }
+    if let Some((uid, gid)) = item.owner {
+        if unsafe { libc::geteuid() } == 0 {
+            let _ = std::os::unix::fs::lchown(target, Some(uid), Some(gid));
+        }
+    }
+    if let Some(mode) = item.mode {
+        let _ = fs::set_permissions(target, fs::Permissions::from_mode(mode));
+    }
+    if let Some(capability) = &item.capability {
+        if let Err(e) = xattr::set(target, CAPABILITY_XATTR, capability) {
+            warn!("{}: cannot restore file capabilities: {e}", target.display());
+        }
+    }
+    if let Some(context) = &item.selinux {
+        let current = xattr::get(target, SELINUX_XATTR).ok().flatten();
+        if current.as_deref().map(|c| c.strip_suffix(b"\0").unwrap_or(c)) != Some(context.as_bytes()) {
+            let mut value = context.clone().into_bytes();
+            value.push(0);
+            if let Err(e) = xattr::set(target, SELINUX_XATTR, &value) {
+                warn!("{}: cannot restore SELinux context {context}: {e}", target.display());
+            }
+        }
+    }
+}
+
+const COMPRESSION_EXTENSIONS: &[&str] = &["gz", "bz2", "xz", "zst", "lz", "lz4", "lzma", "Z", "br"];
+
+fn split_extension(name: &str) -> (&str, &str) {
+    let Some(dot) = name.rfind('.').filter(|&dot| dot > 0) else {
+        return (name, "");
+    };
+    let (stem, ext) = name.split_at(dot);
+    match stem.rfind('.').filter(|&inner| inner > 0 && COMPRESSION_EXTENSIONS.contains(&&ext[1..])) {
+        Some(inner) if stem.len() - inner <= 5 => name.split_at(inner),
+        _ => (stem, ext),
+    }
+}
+
+fn restored_name(original: &Path, attempt: u32) -> PathBuf {
+    let name = original.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "restored".to_owned());
+    let (stem, ext) = split_extension(&name);
+    let date = Local::now().format("%Y-%m-%d");
+    let counter = if attempt > 1 { format!(" {attempt}") } else { String::new() };
+    original.with_file_name(format!("{stem} (restored {date}{counter}){ext}"))
+}
+
+fn case_variant(path: &Path) -> Option<OsString> {
+    let (parent, name) = (path.parent()?, path.file_name()?);
+    if !case_insensitive(parent) {
+        return None;
+    }
+    let wanted = name.to_str()?.to_lowercase();
+    fs::read_dir(parent).ok()?.flatten().map(|entry| entry.file_name())
+        .find(|other| other != name && other.to_str().is_some_and(|other| other.to_lowercase() == wanted))
+}
+
+pub fn restore_item(item: &TrashItem) -> Result<PathBuf> {
+    check_owner(item)?;
+    let src = item.trashed_path();
+    hooks::run("pre_restore", std::slice::from_ref(&item.original_path))?;
+    let is_dir = src.symlink_metadata().is_ok_and(|m| m.is_dir());
+    let mut target = item.original_path.clone();
+    if let Some(existing) = case_variant(&target) {
+        warn!(
+            "{}: {} already exists and the volume ignores case, so the restored item gets a new name",
+            target.display(),
+            existing.to_string_lossy()
+        );
+    }
+    let mut attempt = 1;
+    while target.symlink_metadata().is_ok() {
+        target = restored_name(&item.original_path, attempt);
+        attempt += 1;
+    }
+
+    if let Some(marker) = &item.compressed {
+        decompress_to(&src, marker, &target)?;
+        fs::remove_file(&src)?;
+    } else if let Some(marker) = &item.encrypted {
+        decrypt_to(&src, marker, &target)?;
+        fs::remove_file(&src)?;
+    } else if deduplicated(item) {
+        fs::copy(&src, &target)?;
+        preserve_metadata(&src, &target);
+        fs::remove_file(&src)?;
+    } else if let Err(e) = fs::rename(&src, &target) {
+        if e.raw_os_error() != Some(libc::EXDEV) {
+            return Err(e.into());
+        }
+        debug!("{}: on another filesystem, copying it back", target.display());
+        move_across(&src, &target)?;
+    }
+    if is_dir {
+        forget_directory_size(item.trash_dir(), &item.trashed_name)?;
+    }
+    restore_attributes(item, &target);
+    fs::remove_file(&item.info_path)?;
+    audit::record("restore", &target, item.size);
+    hooks::run("post_restore", std::slice::from_ref(&target))?;
+    Ok(target)
+}
+
+fn parse_selection(text: &str, len: usize) -> Result<Vec<usize>> {
+    if text.eq_ignore_ascii_case("all") {
+        return Ok((0..len).collect());
+    }
+    let mut chosen = Vec::new();
+    for part in text.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) else {
+            return Err(anyhow!("Invalid selection: {part}"));
+        };
+        if start == 0 || end > len || start > end {
+            return Err(anyhow!("No such items: {part} (1-{len})"));
+        }
+        chosen.extend(start - 1..end);
+    }
+    chosen.sort_unstable();
+    chosen.dedup();
+    Ok(chosen)
+}
+
+fn print_selection(items: &[(TrashItem, u64)]) {
+    println!(" # Date & Time              Size  Original Path");
+    println!("────────────────────────────────────────────────────────────────");
+    for (i, (item, size)) in items.iter().enumerate() {
+        let date = item.deletion_date.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        let suffix = if item.kind.as_deref() == Some("dir") { "/" } else { "" };
+        println!("{:>3} {date}  {:>7}  {}{suffix}", i + 1, human_size(*size), item.original_path.display());
+    }
+}
+
+pub fn empty_interactive(tags: &[String], shred: bool) -> Result<()> {
+    let mut items: Vec<(TrashItem, u64)> = iter_trash_items()?
+        .filter(|item| has_tags(item, tags))
+        .map(|item| {
+            let size = item.size.or_else(|| path_size(&item.trashed_path()).ok()).unwrap_or(0);
+            (item, size)
+        })
+        .collect();
+    if items.is_empty() {
+        println!("{}", tr!("trash-empty"));
+        return Ok(());
+    }
+    items.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.deletion_date.cmp(&b.deletion_date)));
+    let chosen = loop {
+        print_selection(&items);
+        print!("Items to delete permanently (e.g. 1 3 5-8, all; s: largest first, o: oldest first; Enter: cancel): ");
+        io::stdout().flush()?;
+        let Some(line) = io::stdin().lock().lines().next().transpose()? else { return Ok(()) };
+        match line.trim() {
+            "" => {
+                println!("{}", tr!("nothing-deleted"));
+                return Ok(());
+            }
+            "s" => items.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.deletion_date.cmp(&b.deletion_date))),
+            "o" => items.sort_by_key(|(item, _)| item.deletion_date),
+            text => match parse_selection(text, items.len()) {
+                Ok(chosen) if !chosen.is_empty() => break chosen,
+                Ok(_) => {}
+                Err(e) => println!("{e}"),
+            },
+        }
+    };
+    let total: u64 = chosen.iter().map(|&i| items[i].1).sum();
+    let kept = items.len() - chosen.len();
+    if !confirm(&tr!("confirm-delete-keep", count = chosen.len(), size = human_size(total), kept = kept)) {
+        println!("{}", tr!("nothing-deleted"));
+        return Ok(());
+    }
+    if shred {
+        eprintln!("{SHRED_CAVEAT}");
+    }
+    let (mut count, mut freed) = (0, 0);
+    for &i in &chosen {
+        let (item, size) = &items[i];
+        let removed = match shred {
+            true => shred_item(item),
+            false => purge_item(item),
+        };
+        match removed {
+            Ok(()) => {
+                count += 1;
+                freed += size;
+            }
+            Err(e) => warn!("{}: {e}", item.original_path.display()),
+        }
+    }
+    println!("{}", tr!("deleted-kept", count = count, size = human_size(freed), kept = kept));
+    Ok(())
+}
+
+pub fn free_space(target: u64) -> Result<()> {
+    let mut items = load_trash_items()?;
+    items.reverse();
+
+    let mut freed = 0;
+    let mut removed = 0;
+    for item in &items {
+        if freed >= target {
+            break;
+        }
+        let size = item.size.or_else(|| path_size(&item.trashed_path()).ok()).unwrap_or(0);
+        match purge_item(item) {
+            Ok(()) => {
+                println!("{}", tr!("freed-item", path = item.original_path.display().to_string(), size = human_size(size)));
+                freed += size;
+                removed += 1;
+            }
+            Err(e) => warn!("{}: {e}", item.original_path.display()),
+        }
+    }
+    println!("{}", tr!("freed", size = human_size(freed), count = removed));
+    if freed < target {
+        return Err(anyhow!("Trash exhausted: only {} could be reclaimed", human_size(freed)));
+    }
+    Ok(())
+}
+
+pub fn empty_trash(shred: bool) -> Result<()> {
+    let roots = trash_roots()?;
+    let doomed: Vec<PathBuf> = roots.iter()
+        .flat_map(|trash| iter_trash_items_in(trash).filter(|item| check_owner(item).is_ok()).map(|item| item.trashed_path()))
+        .collect();
+    hooks::run("pre_empty", &doomed)?;
+    if shred {
+        eprintln!("{SHRED_CAVEAT}");
+    }
+    let (mut count, mut freed, mut others) = (0, 0, 0);
+    let (mut strays_removed, mut strays_kept) = (0, 0);
+    let mut failed = Vec::new();
+    for trash in roots {
+        let mut complete = true;
+        for item in iter_trash_items_in(&trash).collect::<Vec<_>>() {
+            if check_owner(&item).is_err() {
+                others += 1;
+                complete = false;
+                continue;
+            }
+            let removed = match shred {
+                true => shred_path(&item.trashed_path()).and_then(|()| remove_trashed(&item)),
+                false => remove_trashed(&item),
+            };
+            match removed {
+                Ok(_) => {
+                    count += 1;
+                    freed += item.size.unwrap_or(0);
+                    audit::record("empty", &item.original_path, item.size);
+                }
+                Err(e) => {
+                    warn!("{}: {e}", item.original_path.display());
+                    complete = false;
+                    failed.push(item);
+                }
+            }
+        }
+        let stray: Vec<_> = entry_names(&trash.join("files"))
+            .into_iter()
+            .filter(|name| !name.as_bytes().starts_with(b".rip-"))
+            .filter(|name| {
+                let mut info = name.clone();
+                info.push(".trashinfo");
+                !trash.join("info").join(info).exists() && !journal::in_progress(&trash, &name.to_string_lossy())
+            })
+            .collect();
+        if !complete {
+            strays_kept += stray.len();
+        }
+        for name in stray.into_iter().filter(|_| complete) {
+            let path = trash.join("files").join(&name);
+            let removed = if shred { shred_path(&path).and_then(|()| remove_recursively(&path)) } else { remove_recursively(&path) };
+            match removed {
+                Ok(()) => strays_removed += 1,
+                Err(e) => {
+                    debug!("{}: {e}", path.display());
+                    strays_kept += 1;
+                }
+            }
+        }
+        if complete {
+            let _ = fs::remove_file(trash.join("directorysizes"));
+        } else {
+            let files = entry_names(&trash.join("files"));
+            let stale = read_directory_sizes(&trash).into_keys().filter(|name| !files.contains(OsStr::new(name)));
+            for name in stale {
+                forget_directory_size(&trash, &name)?;
+            }
+        }
+    }
+    hooks::run("post_empty", &doomed)?;
+    if others > 0 {
+        println!("{}", tr!("kept-other-users", count = others));
+    }
+    if strays_removed > 0 {
+        println!("{}", tr!("strays-removed", count = strays_removed));
+    }
+    if strays_kept > 0 {
+        println!("{}", tr!("strays-kept", count = strays_kept));
+    }
+    if failed.is_empty() {
+        println!("{}", tr!("trash-emptied", count = count, size = human_size(freed)));
+        return Ok(());
+    }
+    println!("{}", tr!("empty-partial", count = count, size = human_size(freed)));
+    for item in &failed {
+        println!("  {}", item.original_path.display());
+    }
+    Err(anyhow!("{} items could not be removed", failed.len()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(tag: &str) -> Self {
+            let dir = env::temp_dir().join(format!("rip-test-{}-{tag}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // Writes info/NAME.trashinfo as another tool would, plus files/NAME (a file, or a directory for None).
+    fn read_foreign(trash: &Path, name: &str, info: &str, contents: Option<&[u8]>) -> Option<TrashItem> {
+        let files_dir = trash.join("files");
+        fs::create_dir_all(&files_dir).unwrap();
+        fs::create_dir_all(trash.join("info")).unwrap();
+        match contents {
+            Some(bytes) => fs::write(files_dir.join(name), bytes).unwrap(),
+            None => fs::create_dir_all(files_dir.join(name).join("sub")).unwrap(),
+        }
+        let info_path = trash.join("info").join(format!("{name}.trashinfo"));
+        fs::write(&info_path, info).unwrap();
+        let file_names = fs::read_dir(&files_dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        read_trash_item(info_path, &files_dir, &HashMap::new(), &file_names)
+    }
+
+    fn local(date: &str) -> DateTime<Utc> {
+        let naive = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn reads_gio_trashinfo() {
+        let tmp = TempDir::new("gio");
+        let info = "[Trash Info]\nPath=/home/alice/Documents/report%20final%20caf%C3%A9.pdf\nDeletionDate=2024-03-05T14:22:01\n";
+        let item = read_foreign(&tmp.0.join("Trash"), "report final café.pdf", info, Some(b"%PDF-1.7")).unwrap();
+        assert_eq!(item.original_path, Path::new("/home/alice/Documents/report final café.pdf"));
+        assert_eq!(item.deletion_date, local("2024-03-05T14:22:01"));
+        assert_eq!(item.size, Some(8));
+        assert_eq!((item.tags.len(), item.trashed_by, item.sequence), (0, None, None));
+    }
+
+    #[test]
+    fn reads_nautilus_directory_without_size() {
+        let tmp = TempDir::new("nautilus");
+        let info = "[Trash Info]\nPath=/home/alice/Projects/old%20site\nDeletionDate=2023-11-30T08:05:59\n";
+        let item = read_foreign(&tmp.0.join("Trash"), "old site", info, None).unwrap();
+        assert_eq!(item.original_path, Path::new("/home/alice/Projects/old site"));
+        assert_eq!(item.deletion_date, local("2023-11-30T08:05:59"));
+        assert_eq!(item.size, None);
+        assert_eq!(item.kind, None);
+    }
+
+    #[test]
+    fn reads_trash_cli_volume_relative_path() {
+        let tmp = TempDir::new("trash-cli");
+        let info = "[Trash Info]\nPath=projects/notes%20v2.txt\nDeletionDate=2024-07-14T23:59:59\n";
+        let item = read_foreign(&tmp.0.join(".Trash-1000"), "notes v2.txt", info, Some(b"todo\n")).unwrap();
+        assert_eq!(item.original_path, tmp.0.join("projects/notes v2.txt"));
+        assert_eq!(item.deletion_date, local("2024-07-14T23:59:59"));
+        assert_eq!(item.size, Some(5));
+    }
+
+    #[test]
+    fn reads_non_utf8_percent_encoded_path() {
+        let tmp = TempDir::new("non-utf8");
+        let info = "[Trash Info]\nPath=/srv/data/%FF%FEraw.bin\nDeletionDate=2024-01-02T03:04:05\n";
+        let item = read_foreign(&tmp.0.join("Trash"), "raw.bin", info, Some(b"")).unwrap();
+        assert_eq!(item.original_path.as_os_str().as_bytes(), b"/srv/data/\xff\xferaw.bin");
+    }
+
+    #[test]
+    fn flush_writes_the_records_after_a_failed_one() {
+        let tmp = TempDir::new("flush");
+        let info = tmp.0.join("info");
+        fs::create_dir_all(&info).unwrap();
+        let mut batch = InfoBatch::with_limit(10);
+        for name in ["first", "lost/second", "third"] {
+            batch.push(info.join(format!("{name}.trashinfo")), format!("[Trash Info]\nPath=/{name}\n"), None).unwrap();
+        }
+
+        assert!(batch.flush().is_err());
+        assert!(info.join("first.trashinfo").exists());
+        assert_eq!(fs::read_to_string(info.join("third.trashinfo")).unwrap(), "[Trash Info]\nPath=/third\n");
+    }
+
+    #[test]
+    fn skips_trashinfo_without_trashed_file() {
+        let tmp = TempDir::new("orphan");
+        let trash = tmp.0.join("Trash");
+        read_foreign(&trash, "present", "[Trash Info]\nPath=/a\nDeletionDate=2024-01-02T03:04:05\n", Some(b"x")).unwrap();
+        let info_path = trash.join("info/missing.trashinfo");
+        fs::write(&info_path, "[Trash Info]\nPath=/b\nDeletionDate=2024-01-02T03:04:05\n").unwrap();
+        let file_names = fs::read_dir(trash.join("files")).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert!(read_trash_item(info_path, &trash.join("files"), &HashMap::new(), &file_names).is_none());
+    }
+
+    #[test]
+    fn parses_deletion_dates_of_other_tools() {
+        assert_eq!(parse_deletion_date("2024-03-05T14:22:01"), Some(local("2024-03-05T14:22:01")));
+        assert_eq!(parse_deletion_date("2024-03-05 14:22:01"), Some(local("2024-03-05T14:22:01")));
+        assert_eq!(parse_deletion_date("2024-03-05T14:22:01.250").map(|d| d.timestamp_subsec_millis()), Some(250));
+        assert_eq!(parse_deletion_date("2024-03-05T14:22:01+02:00"), Some(Utc.with_ymd_and_hms(2024, 3, 5, 12, 22, 1).unwrap()));
+        assert_eq!(parse_deletion_date("yesterday"), None);
+    }
+
+    #[test]
+    fn deletion_dates_round_trip_in_the_spec_format() {
+        for date in ["2024-03-05T14:22:01", "1999-12-31T23:59:59", "2024-02-29T00:00:00"] {
+            assert_eq!(format_deletion_date(parse_deletion_date(date).unwrap()), date);
+        }
+    }
+}
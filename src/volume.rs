@@ -0,0 +1,243 @@
+// src/volume.rs - Per-volume $topdir/.Trash directories as described by the freedesktop spec
+use crate::config;
+use anyhow::{anyhow, Result};
+use std::ffi::OsString;
+use std::fs::{self, DirBuilder};
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "proc", "sysfs", "devtmpfs", "devpts", "cgroup", "cgroup2", "securityfs", "debugfs", "tracefs",
+    "pstore", "bpf", "configfs", "fusectl", "mqueue", "hugetlbfs", "autofs", "binfmt_misc", "squashfs",
+    "efivarfs", "rpc_pipefs", "nsfs",
+];
+
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ncpfs", "afs", "ceph", "9p", "glusterfs", "lustre", "davfs",
+    "fuse.sshfs", "fuse.rclone", "fuse.glusterfs", "fuse.davfs2", "fuse.s3fs",
+];
+
+fn uid() -> u32 {
+    crate::sudo::trash_uid()
+}
+
+fn unescape_mount_point(field: &str) -> PathBuf {
+    let mut bytes = Vec::with_capacity(field.len());
+    let raw = field.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\' && i + 3 < raw.len() {
+            if let Ok(byte) = u8::from_str_radix(&field[i + 1..i + 4], 8) {
+                bytes.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+fn mounts() -> Vec<(PathBuf, String)> {
+    let Ok(mountinfo) = fs::read_to_string("/proc/self/mountinfo") else {
+        return Vec::new();
+    };
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (mount, fs) = line.split_once(" - ")?;
+            let fstype = fs.split(' ').next()?;
+            let point = mount.split(' ').nth(4)?;
+            Some((unescape_mount_point(point), fstype.to_owned()))
+        })
+        .collect()
+}
+
+pub fn mount_points() -> Vec<PathBuf> {
+    mounts()
+        .into_iter()
+        .filter(|(_, fstype)| !PSEUDO_FILESYSTEMS.contains(&fstype.as_str()))
+        .map(|(point, _)| point)
+        .collect()
+}
+
+pub fn is_mount_point(dir: &Path) -> bool {
+    dir.canonicalize().is_ok_and(|dir| mounts().iter().any(|(point, _)| *point == dir))
+}
+
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = dir.canonicalize().ok()?;
+    mounts()
+        .into_iter()
+        .filter(|(point, _)| dir.starts_with(point))
+        .max_by_key(|(point, _)| point.components().count())
+        .map(|(_, fstype)| fstype)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkFs {
+    Volume,
+    Copy,
+    Delete,
+}
+
+pub fn parse_network_fs(text: &str) -> Result<NetworkFs> {
+    match text.trim().to_lowercase().as_str() {
+        "volume" => Ok(NetworkFs::Volume),
+        "copy" => Ok(NetworkFs::Copy),
+        "delete" => Ok(NetworkFs::Delete),
+        other => Err(anyhow!("Invalid network_fs {other:?} (expected volume, copy or delete)")),
+    }
+}
+
+pub fn network_fs_policy() -> NetworkFs {
+    config::get()
+        .network_fs
+        .as_deref()
+        .and_then(|p| parse_network_fs(p).ok())
+        .unwrap_or(NetworkFs::Volume)
+}
+
+pub fn remote_filesystem(path: &Path, home_trash: &Path) -> Option<String> {
+    let dev = path.symlink_metadata().ok()?.dev();
+    if home_trash.metadata().ok()?.dev() == dev {
+        return None;
+    }
+    filesystem_type(path).filter(|fstype| NETWORK_FILESYSTEMS.contains(&fstype.as_str()))
+}
+
+pub fn topdir_of(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = parent.canonicalize().ok()?;
+    let dev = dir.metadata().ok()?.dev();
+    let mut topdir = dir.clone();
+    for ancestor in dir.ancestors().skip(1) {
+        if ancestor.metadata().ok()?.dev() != dev {
+            break;
+        }
+        topdir = ancestor.to_path_buf();
+    }
+    Some(topdir)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SharedRoot {
+    Missing,
+    Usable,
+    Rejected(&'static str),
+}
+
+pub fn check_shared_root(dir: &Path) -> SharedRoot {
+    let Ok(meta) = dir.symlink_metadata() else { return SharedRoot::Missing };
+    if meta.file_type().is_symlink() {
+        SharedRoot::Rejected("a symlink")
+    } else if !meta.is_dir() {
+        SharedRoot::Rejected("not a directory")
+    } else if meta.mode() & libc::S_ISVTX == 0 {
+        SharedRoot::Rejected("not a sticky directory")
+    } else if meta.uid() != 0 && meta.uid() != uid() {
+        SharedRoot::Rejected("owned by another user, who could remove everyone's items")
+    } else {
+        SharedRoot::Usable
+    }
+}
+
+fn owned_dir(dir: &Path) -> bool {
+    dir.symlink_metadata().is_ok_and(|m| m.is_dir() && m.uid() == uid())
+}
+
+fn prepare(trash: &Path) -> bool {
+    let mut builder = DirBuilder::new();
+    builder.mode(0o700);
+    let create = |dir: &Path| {
+        let created = builder.create(dir).is_ok();
+        if created {
+            crate::sudo::chown_to_user(dir);
+        }
+        created
+    };
+    if trash.symlink_metadata().is_err() && !create(trash) {
+        return false;
+    }
+    owned_dir(trash)
+        && ["files", "info"].iter().all(|sub| {
+            let dir = trash.join(sub);
+            owned_dir(&dir) || create(&dir)
+        })
+}
+
+pub fn trash_candidates(topdir: &Path) -> [PathBuf; 2] {
+    [topdir.join(".Trash").join(uid().to_string()), topdir.join(format!(".Trash-{}", uid()))]
+}
+
+pub fn volume_trash_for(path: &Path, home_trash: &Path) -> Option<PathBuf> {
+    let dev = path.symlink_metadata().ok()?.dev();
+    if home_trash.metadata().ok()?.dev() == dev {
+        return None;
+    }
+    let topdir = topdir_of(path)?;
+    let [shared, private] = trash_candidates(&topdir);
+    let shared_root = topdir.join(".Trash");
+    match check_shared_root(&shared_root) {
+        SharedRoot::Usable if prepare(&shared) => return Some(shared),
+        SharedRoot::Usable => warn!("ignoring {}: not a directory owned by you", shared.display()),
+        SharedRoot::Rejected(reason) => warn!("ignoring {}: {reason}", shared_root.display()),
+        SharedRoot::Missing => {}
+    }
+    prepare(&private).then_some(private)
+}
+
+pub fn planned_volume_trash(path: &Path, home_trash: &Path) -> Option<PathBuf> {
+    let dev = path.symlink_metadata().ok()?.dev();
+    if home_trash.metadata().ok()?.dev() == dev {
+        return None;
+    }
+    let topdir = topdir_of(path)?;
+    let [shared, private] = trash_candidates(&topdir);
+    match check_shared_root(&topdir.join(".Trash")) == SharedRoot::Usable && (owned_dir(&shared) || shared.symlink_metadata().is_err()) {
+        true => Some(shared),
+        false => Some(private),
+    }
+}
+
+pub fn volume_trashes(home_trash: &Path) -> Vec<PathBuf> {
+    let mut trashes = Vec::new();
+    for topdir in mount_points() {
+        let [shared, private] = trash_candidates(&topdir);
+        let shared = (check_shared_root(&topdir.join(".Trash")) == SharedRoot::Usable).then_some(shared);
+        for trash in shared.into_iter().chain([private]) {
+            if owned_dir(&trash) && trash != home_trash && !trashes.contains(&trash) {
+                trashes.push(trash);
+            }
+        }
+    }
+    trashes
+}
+
+pub fn trash_topdir(trash: &Path) -> Option<&Path> {
+    let parent = trash.parent()?;
+    if parent.file_name().is_some_and(|n| n == ".Trash") {
+        parent.parent()
+    } else {
+        Some(parent)
+    }
+}
+
+pub fn path_within_volume(path: &Path, trash: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let absolute = parent.canonicalize().ok()?.join(path.file_name()?);
+    absolute.strip_prefix(trash_topdir(trash)?).ok().map(Path::to_path_buf)
+}
@@ -0,0 +1,247 @@
+// src/watch.rs - Watch rules ("~/Downloads/*.iso after 14d") that let the daemon trash files once they are old enough
+use crate::config;
+use crate::duration::Span;
+use crate::trash::{move_to_trash, state_dir, InfoBatch};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, Utc};
+use glob::{MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use urlencoding::{decode_binary, encode_binary};
+use walkdir::WalkDir;
+
+const SEEN_FILE: &str = "watch-seen";
+const LOG_FILE: &str = "watch.log";
+const EVENTS: u32 = libc::IN_CREATE | libc::IN_MOVED_TO | libc::IN_CLOSE_WRITE;
+const MATCH: MatchOptions = MatchOptions { case_sensitive: true, require_literal_separator: true, require_literal_leading_dot: false };
+
+pub struct WatchRule {
+    pattern: Pattern,
+    after: Span,
+}
+
+pub fn parse_watch_rule(text: &str) -> Result<WatchRule> {
+    let (pattern, after) = text
+        .trim()
+        .rsplit_once(" after ")
+        .ok_or_else(|| anyhow!("expected \"PATTERN after SPAN\", e.g. \"~/Downloads/*.iso after 14d\""))?;
+    let pattern = config::expand_path(pattern.trim());
+    Ok(WatchRule {
+        pattern: Pattern::new(&pattern.to_string_lossy()).with_context(|| format!("Invalid pattern {pattern:?}"))?,
+        after: Span::parse(after)?,
+    })
+}
+
+impl WatchRule {
+    fn base_dir(&self) -> PathBuf {
+        let pattern = PathBuf::from(self.pattern.as_str());
+        let base: PathBuf = pattern
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+            .collect();
+        match base == pattern {
+            true => base.parent().map(Path::to_path_buf).unwrap_or(base),
+            false => base,
+        }
+    }
+
+    fn recursive(&self) -> bool {
+        self.pattern.as_str().contains("**")
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        self.pattern.matches_path_with(path, MATCH)
+    }
+}
+
+pub struct Watcher {
+    inotify: Option<OwnedFd>,
+    dirs: HashMap<i32, PathBuf>,
+    rules: Vec<WatchRule>,
+    seen: HashMap<PathBuf, DateTime<Utc>>,
+}
+
+impl Watcher {
+    pub fn start() -> Option<Self> {
+        let rules: Vec<_> = config::get().watch.iter().filter_map(|rule| parse_watch_rule(rule).ok()).collect();
+        if rules.is_empty() {
+            return None;
+        }
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        let inotify = match fd {
+            -1 => {
+                warn!("inotify unavailable ({}); watch rules go by change times only", io::Error::last_os_error());
+                None
+            }
+            fd => Some(unsafe { OwnedFd::from_raw_fd(fd) }),
+        };
+        let mut watcher = Watcher { inotify, dirs: HashMap::new(), rules, seen: load_seen() };
+        let bases: Vec<_> = watcher.rules.iter().map(|rule| (rule.base_dir(), rule.recursive())).collect();
+        for (base, recursive) in bases {
+            match recursive {
+                true => WalkDir::new(&base)
+                    .into_iter()
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_dir())
+                    .for_each(|entry| watcher.add_watch(entry.path())),
+                false => watcher.add_watch(&base),
+            }
+        }
+        println!("Watching {} rules", watcher.rules.len());
+        Some(watcher)
+    }
+
+    fn add_watch(&mut self, dir: &Path) {
+        let (Some(inotify), Ok(c_dir)) = (&self.inotify, CString::new(dir.as_os_str().as_bytes())) else { return };
+        match unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), c_dir.as_ptr(), EVENTS | libc::IN_ONLYDIR) } {
+            -1 => debug!("{}: cannot watch: {}", dir.display(), io::Error::last_os_error()),
+            wd => {
+                self.dirs.insert(wd, dir.to_path_buf());
+            }
+        }
+    }
+
+    pub fn wait(&mut self, timeout: Duration) {
+        let Some(fd) = self.inotify.as_ref().map(AsRawFd::as_raw_fd) else {
+            thread::sleep(timeout);
+            return;
+        };
+        let deadline = Instant::now() + timeout;
+        while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+            let mut poll = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+            if unsafe { libc::poll(&mut poll, 1, left.as_millis().max(1) as i32) } > 0 {
+                self.read_events(fd);
+            }
+        }
+    }
+
+    fn read_events(&mut self, inotify: i32) {
+        let mut buf = vec![0u8; 64 * 1024];
+        let len = unsafe { libc::read(inotify, buf.as_mut_ptr().cast(), buf.len()) };
+        let mut offset = 0;
+        let mut changed = false;
+        while len > 0 && offset + std::mem::size_of::<libc::inotify_event>() <= len as usize {
+            let event: libc::inotify_event = unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+            let name_start = offset + std::mem::size_of::<libc::inotify_event>();
+            let name = &buf[name_start..name_start + event.len as usize];
+            let name = OsStr::from_bytes(name.split(|b| *b == 0).next().unwrap_or_default());
+            offset = name_start + event.len as usize;
+            let Some(path) = self.dirs.get(&event.wd).map(|dir| dir.join(name)) else { continue };
+            if event.mask & libc::IN_ISDIR != 0 {
+                if self.rules.iter().any(|rule| rule.recursive() && path.starts_with(rule.base_dir())) {
+                    self.add_watch(&path);
+                }
+            } else if self.rules.iter().any(|rule| rule.matches(&path)) {
+                debug!("{}: seen by a watch rule", path.display());
+                self.seen.insert(path, Utc::now());
+                changed = true;
+            }
+        }
+        if changed {
+            save_seen(&self.seen);
+        }
+    }
+
+    pub fn sweep(&mut self) {
+        let now = Utc::now();
+        let mut batch = InfoBatch::new();
+        for rule in &self.rules {
+            let Ok(paths) = glob::glob_with(rule.pattern.as_str(), MATCH) else { continue };
+            for path in paths.flatten() {
+                let Ok(meta) = path.symlink_metadata() else { continue };
+                if meta.is_dir() {
+                    continue;
+                }
+                let arrived = self.seen.get(&path).copied().or_else(|| DateTime::from_timestamp(meta.ctime(), 0));
+                if arrived.is_none_or(|t| t > rule.after.before(now)) {
+                    continue;
+                }
+                match move_to_trash(&path.to_string_lossy(), &mut batch) {
+                    Ok(()) => {
+                        println!("Swept {} (watch rule {} after {})", path.display(), rule.pattern, rule.after);
+                        log_swept(&path, rule);
+                        self.seen.remove(&path);
+                    }
+                    Err(e) => warn!("{}: watch rule could not trash it: {e}", path.display()),
+                }
+            }
+        }
+        if let Err(e) = batch.flush() {
+            warn!("cannot record swept items: {e}");
+        }
+        let before = self.seen.len();
+        self.seen.retain(|path, _| path.symlink_metadata().is_ok());
+        if self.seen.len() != before {
+            save_seen(&self.seen);
+        }
+    }
+}
+
+fn load_seen() -> HashMap<PathBuf, DateTime<Utc>> {
+    let Ok(content) = state_dir().and_then(|dir| Ok(fs::read_to_string(dir.join(SEEN_FILE))?)) else {
+        return HashMap::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let (time, path) = line.split_once('\t')?;
+            let time = DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc);
+            Some((PathBuf::from(OsString::from_vec(decode_binary(path.as_bytes()).into_owned())), time))
+        })
+        .collect()
+}
+
+fn save_seen(seen: &HashMap<PathBuf, DateTime<Utc>>) {
+    let content: String = seen
+        .iter()
+        .map(|(path, time)| format!("{}\t{}\n", time.to_rfc3339(), encode_binary(path.as_os_str().as_bytes())))
+        .collect();
+    if let Err(e) = state_dir().and_then(|dir| Ok(fs::write(dir.join(SEEN_FILE), content)?)) {
+        warn!("cannot save watch state: {e}");
+    }
+}
+
+fn log_swept(path: &Path, rule: &WatchRule) {
+    let logged = state_dir().and_then(|dir| {
+        let mut log = OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE))?;
+        writeln!(log, "{}\t{}\t{} after {}", Local::now().format("%Y-%m-%d %H:%M:%S"), path.display(), rule.pattern, rule.after)?;
+        Ok(())
+    });
+    if let Err(e) = logged {
+        warn!("cannot write the watch log: {e}");
+    }
+}
+
+pub fn setup_downloads_janitor(after_days: u32, expire_days: u32) -> Result<()> {
+    let downloads = dirs_next::download_dir()
+        .or_else(|| dirs_next::home_dir().map(|home| home.join("Downloads")))
+        .context("Cannot determine the Downloads directory")?;
+    let shown = match dirs_next::home_dir().and_then(|home| downloads.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => downloads.display().to_string(),
+    };
+    let prefix = format!("{shown}/* after ");
+    let rule = format!("{prefix}{after_days}d");
+    parse_watch_rule(&rule)?;
+    let mut rules: toml_edit::Array = config::get().watch.iter().filter(|existing| !existing.starts_with(&prefix)).collect();
+    rules.push(rule);
+    config::set_entry(&["watch"], rules.into())?;
+    config::set_entry(&["policy", &shown, "keep"], format!("{expire_days}d").into())?;
+    println!("Downloads janitor set up:");
+    println!("  files in {shown} are trashed {after_days} days after they arrive");
+    println!("  trashed downloads are deleted for good {expire_days} days later");
+    match crate::daemon::daemon_running() {
+        true => println!("Restart rip --daemon to pick up the watch rule."),
+        false => println!("Watch rules run inside rip --daemon; start it (e.g. from your session autostart) to activate them."),
+    }
+    Ok(())
+}